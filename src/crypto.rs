@@ -0,0 +1,99 @@
+use crate::{parse_tasks, TaskFile};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Marks an encrypted task file. It is followed by the Argon2 salt, the nonce
+/// and the XChaCha20-Poly1305 ciphertext of the usual JSON contents.
+const MAGIC: &[u8] = b"TODOENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+pub const PASSPHRASE_ENV: &str = "TODO_PASSPHRASE";
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = derive_cipher(passphrase, &salt)?
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Failed to encrypt tasks".to_string())?;
+
+    let mut data = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let body = &data[MAGIC.len()..];
+    if body.len() < SALT_LEN + NONCE_LEN {
+        return Err("Encrypted task file is truncated".to_string());
+    }
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    derive_cipher(passphrase, salt)?
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong passphrase, or the task file is corrupted".to_string())
+}
+
+/// Reads the passphrase from `TODO_PASSPHRASE`, or prompts for it without echo.
+pub fn passphrase(prompt: &str) -> Result<String, String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read passphrase: {}", e))
+}
+
+/// Asks for a new passphrase twice (unless `TODO_PASSPHRASE` is set) and
+/// rejects empty or mismatched entries.
+pub fn new_passphrase() -> Result<String, String> {
+    let entered = passphrase("New passphrase: ")?;
+    if entered.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+    if env::var(PASSPHRASE_ENV).is_err() && passphrase("Repeat passphrase: ")? != entered {
+        return Err("Passphrases don't match".to_string());
+    }
+    Ok(entered)
+}
+
+/// Loads a task file that may or may not be encrypted. `known` is tried first
+/// so reloads don't prompt again; the passphrase that worked is returned with
+/// the parsed file, or `None` for a plain JSON file.
+pub fn read_task_file(
+    path: &Path,
+    known: Option<String>,
+) -> Result<(TaskFile, Option<String>), String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (contents, key) = if is_encrypted(&data) {
+        let key = match known {
+            Some(key) => key,
+            None => passphrase(&format!("Passphrase for {}: ", path.display()))?,
+        };
+        (decrypt(&data, &key)?, Some(key))
+    } else {
+        (data, None)
+    };
+    let file =
+        parse_tasks(&contents).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok((file, key))
+}
@@ -0,0 +1,326 @@
+use crate::{Priority, Task, DEFAULT_DATE_FORMAT};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+
+pub fn to_json(tasks: &[&Task]) -> Result<String, String> {
+    crate::json_string(tasks).map_err(|e| format!("Failed to serialize tasks: {}", e))
+}
+
+/// Newline-delimited JSON: one compact task object per line.
+pub fn to_jsonl(tasks: &[&Task]) -> Result<String, String> {
+    let mut out = String::new();
+    for task in tasks {
+        let line = serde_json::to_string(task)
+            .map_err(|e| format!("Failed to serialize task {}: {}", task.id, e))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub const CSV_HEADER: &str =
+    "id,title,description,completed,priority,category,tags,due_date,created_at,completed_at";
+
+pub fn to_csv(tasks: &[&Task]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for task in tasks {
+        let fields = [
+            task.id.to_string(),
+            task.title.clone(),
+            task.description.clone().unwrap_or_default(),
+            task.completed.to_string(),
+            format!("{:?}", task.priority).to_lowercase(),
+            task.category.name().to_string(),
+            task.tags.join(";"),
+            task.due_date
+                .map(|d| d.format(DEFAULT_DATE_FORMAT).to_string())
+                .unwrap_or_default(),
+            task.created_at.to_rfc3339(),
+            task.completed_at
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits CSV text into records, honoring quoted fields with `""` escapes and
+/// embedded newlines.
+fn csv_records(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// A 1-based CSV data row number and the task parsed from it.
+pub type CsvRow = (usize, Result<Task, String>);
+
+/// Reads CSV in the layout `to_csv` writes. Each data row becomes a task or a
+/// per-row error.
+pub fn from_csv(contents: &str) -> Result<Vec<CsvRow>, String> {
+    let mut records = csv_records(contents)?.into_iter();
+    let header = records.next().unwrap_or_default();
+    if header.join(",") != CSV_HEADER {
+        return Err(format!("expected the CSV header: {}", CSV_HEADER));
+    }
+    Ok(records
+        .filter(|record| record.iter().any(|f| !f.is_empty()))
+        .enumerate()
+        .map(|(index, record)| (index + 1, csv_task(&record)))
+        .collect())
+}
+
+fn csv_task(fields: &[String]) -> Result<Task, String> {
+    let [id, title, description, completed, priority, category, tags, due_date, created_at, completed_at] =
+        fields
+    else {
+        return Err(format!("expected 10 fields, found {}", fields.len()));
+    };
+    let id = id.parse().map_err(|_| format!("invalid id '{}'", id))?;
+    let timestamp = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|d| d.with_timezone(&Local))
+            .map_err(|e| format!("invalid timestamp '{}': {}", value, e))
+    };
+
+    let mut task = Task::new(
+        id,
+        title.clone(),
+        Some(description.clone()).filter(|d| !d.is_empty()),
+        None,
+        priority,
+        category,
+        Some(tags.replace(';', ",")),
+    )?;
+    task.completed = completed
+        .parse()
+        .map_err(|_| format!("invalid completed value '{}'", completed))?;
+    if !due_date.is_empty() {
+        task.due_date = Some(
+            NaiveDateTime::parse_from_str(due_date, DEFAULT_DATE_FORMAT)
+                .map_err(|e| format!("invalid due date '{}': {}", due_date, e))?,
+        );
+    }
+    if !created_at.is_empty() {
+        task.created_at = timestamp(created_at)?;
+    }
+    if !completed_at.is_empty() {
+        task.completed_at = Some(timestamp(completed_at)?);
+    }
+    Ok(task)
+}
+
+/// Two-column `label,value` CSV, used for key/value views like stats.
+pub fn key_values_to_csv(header: [&str; 2], rows: &[(String, String)]) -> String {
+    let mut out = format!("{},{}\n", header[0], header[1]);
+    for (label, value) in rows {
+        out.push_str(&format!("{},{}\n", csv_field(label), csv_field(value)));
+    }
+    out
+}
+
+/// Two-column markdown table, used for key/value views like stats.
+pub fn key_values_to_markdown(header: [&str; 2], rows: &[(String, String)]) -> String {
+    let mut out = format!("| {} | {} |\n|---|---|\n", header[0], header[1]);
+    for (label, value) in rows {
+        out.push_str(&format!(
+            "| {} | {} |\n",
+            markdown_cell(label),
+            markdown_cell(value)
+        ));
+    }
+    out
+}
+
+pub fn to_markdown(tasks: &[&Task]) -> String {
+    let mut out = String::from("| | ID | Title | Due Date | Priority | Category | Tags |\n");
+    out.push_str("|---|---|---|---|---|---|---|\n");
+    for task in tasks {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {:?} | {} | {} |\n",
+            if task.completed { "[x]" } else { "[ ]" },
+            task.id,
+            markdown_cell(&task.title),
+            task.due_date
+                .map(|d| d.format(crate::config().date_format()).to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            task.priority,
+            markdown_cell(task.category.name()),
+            markdown_cell(&task.tags.join(", ")),
+        ));
+    }
+    out
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.4em 0.6em; text-align: left; }
+th { background: #f0f0f0; }
+tr.completed td { color: #888; text-decoration: line-through; }
+tr.overdue td.due { color: #c00; font-weight: bold; }
+td.priority-low { color: #555; }
+td.priority-medium { color: #b8860b; }
+td.priority-high { color: #e0443e; }
+td.priority-critical { color: #c00; font-weight: bold; }
+";
+
+pub fn to_html(tasks: &[&Task]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Tasks</title>\n");
+    out.push_str(&format!("<style>{}</style>\n", HTML_STYLE));
+    out.push_str("</head>\n<body>\n<h1>Tasks</h1>\n<table>\n");
+    out.push_str("<tr><th>ID</th><th>Status</th><th>Title</th><th>Due Date</th>");
+    out.push_str("<th>Priority</th><th>Category</th><th>Tags</th></tr>\n");
+    for task in tasks {
+        let mut classes = Vec::new();
+        if task.completed {
+            classes.push("completed");
+        }
+        if task.is_overdue() {
+            classes.push("overdue");
+        }
+        let priority = format!("{:?}", task.priority);
+        out.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td class=\"due\">{}</td>\
+             <td class=\"priority-{}\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            classes.join(" "),
+            task.id,
+            if task.completed { "✓" } else { "✗" },
+            html_escape(&task.title),
+            task.due_date
+                .map(|d| d.format(crate::config().date_format()).to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            priority.to_lowercase(),
+            priority,
+            html_escape(task.category.name()),
+            html_escape(&task.tags.join(", ")),
+        ));
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn to_ics(tasks: &[&Task]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rust-cli-app//todo//EN".to_string(),
+    ];
+    for task in tasks {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:task-{}@rust-cli-app", task.id));
+        lines.push(format!(
+            "DTSTAMP:{}",
+            task.created_at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ")
+        ));
+        lines.push(format!("SUMMARY:{}", ics_text(&task.title)));
+        if let Some(description) = &task.description {
+            lines.push(format!("DESCRIPTION:{}", ics_text(description)));
+        }
+        if let Some(due) = task.due_date {
+            lines.push(format!("DUE:{}", due.format("%Y%m%dT%H%M%S")));
+        }
+        lines.push(format!("PRIORITY:{}", ics_priority(task.priority)));
+        lines.push(format!("CATEGORIES:{}", ics_text(task.category.name())));
+        if task.completed {
+            lines.push("STATUS:COMPLETED".to_string());
+            if let Some(completed_at) = task.completed_at {
+                lines.push(format!(
+                    "COMPLETED:{}",
+                    completed_at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ")
+                ));
+            }
+        } else {
+            lines.push("STATUS:NEEDS-ACTION".to_string());
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_ics_line(line)).collect()
+}
+
+fn ics_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Critical => 1,
+        Priority::High => 3,
+        Priority::Medium => 5,
+        Priority::Low => 7,
+    }
+}
+
+fn ics_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line so no physical line exceeds 75 octets (RFC 5545 §3.1),
+/// terminating every line with CRLF.
+fn fold_ics_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
@@ -1,12 +1,35 @@
-use chrono::{DateTime, Local, NaiveDateTime};
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::*;
-use prettytable::{Cell, Row, Table, row};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use prettytable::format::FormatBuilder;
+use prettytable::{Cell, Row, Table};
 use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::BuildHasher;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Instant;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg(feature = "encrypt")]
+// Only the JSON store is encrypted, so most of this is unused in sqlite builds.
+#[cfg_attr(feature = "sqlite", allow(dead_code))]
+mod crypto;
+mod export;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod template;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum Priority {
     Low,
     Medium,
@@ -14,6 +37,84 @@ enum Priority {
     Critical,
 }
 
+impl Priority {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name.to_lowercase().as_str() {
+            "low" => Priority::Low,
+            "medium" => Priority::Medium,
+            "high" => Priority::High,
+            "critical" => Priority::Critical,
+            other => {
+                return Err(
+                    match closest_match(other, &["low", "medium", "high", "critical"]) {
+                        Some(hint) => format!(
+                            "Invalid priority level '{}', did you mean '{}'?",
+                            name, hint
+                        ),
+                        None => "Invalid priority level".to_string(),
+                    },
+                )
+            }
+        })
+    }
+
+    fn raised(self) -> Self {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High | Priority::Critical => Priority::Critical,
+        }
+    }
+
+    fn lowered(self) -> Self {
+        match self {
+            Priority::Critical => Priority::High,
+            Priority::High => Priority::Medium,
+            Priority::Medium | Priority::Low => Priority::Low,
+        }
+    }
+
+    fn colored(self) -> ColoredString {
+        self.style(&format!("{:?}", self))
+    }
+
+    /// Applies this priority's color to `text`.
+    fn style(self, text: &str) -> ColoredString {
+        match self {
+            Priority::Low => text.normal(),
+            Priority::Medium => text.yellow(),
+            Priority::High => text.bright_red(),
+            Priority::Critical => text.red().bold(),
+        }
+    }
+}
+
+fn colored_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| match config().tag_color(tag) {
+            Some(color) => tag.color(color).to_string(),
+            None => tag.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn status_symbol(completed: bool) -> ColoredString {
+    if completed {
+        "✓".green()
+    } else {
+        "✗".red()
+    }
+}
+
+fn blocked_symbol() -> ColoredString {
+    "⛔ blocked".yellow()
+}
+
+fn due_today_symbol() -> ColoredString {
+    "⏰ today".yellow()
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 enum Category {
     Personal,
@@ -23,17 +124,95 @@ enum Category {
     Other(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Category {
+    /// Parses a category name. Custom categories are trimmed and lowercased so
+    /// that "Chores" and " chores" end up as the same category.
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "" => return Err("Category cannot be empty".to_string()),
+            "personal" => Category::Personal,
+            "work" => Category::Work,
+            "shopping" => Category::Shopping,
+            "health" => Category::Health,
+            other => Category::Other(other.to_string()),
+        })
+    }
+
+    /// The built-in category a custom name is probably a typo of, if any.
+    fn suggestion(name: &str) -> Option<&'static str> {
+        match Category::parse(name) {
+            Ok(Category::Other(other)) => {
+                closest_match(&other, &["personal", "work", "shopping", "health"])
+            }
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Category::Personal => "Personal",
+            Category::Work => "Work",
+            Category::Shopping => "Shopping",
+            Category::Health => "Health",
+            Category::Other(s) => s,
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name().eq_ignore_ascii_case(name.trim())
+    }
+
+    /// Matches a `move-category` selector: comma-separated names, each of
+    /// which may use `*` as a wildcard, e.g. `work,proj-*`.
+    fn matches_selector(&self, selector: &str) -> bool {
+        let name = self.name().to_lowercase();
+        selector
+            .split(',')
+            .map(|pattern| pattern.trim().to_lowercase())
+            .filter(|pattern| !pattern.is_empty())
+            .any(|pattern| wildcard_match(&pattern, &name))
+    }
+
+    fn colored(&self) -> ColoredString {
+        match self {
+            Category::Personal => self.name().green(),
+            Category::Work => self.name().blue(),
+            Category::Shopping => self.name().magenta(),
+            Category::Health => self.name().cyan(),
+            Category::Other(_) => self.name().normal(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Task {
     id: usize,
     title: String,
     description: Option<String>,
     completed: bool,
+    #[serde(default)]
+    completed_at: Option<DateTime<Local>>,
     created_at: DateTime<Local>,
     due_date: Option<NaiveDateTime>,
     priority: Priority,
     category: Category,
     tags: Vec<String>,
+    #[serde(default)]
+    blocked_by: Vec<usize>,
+    #[serde(default)]
+    parent: Option<usize>,
+    /// Estimated effort in minutes.
+    #[serde(default)]
+    estimate: Option<u32>,
+    /// Pinned tasks are listed before all others.
+    #[serde(default)]
+    pinned: bool,
+    /// Timestamped progress notes, oldest first.
+    #[serde(default)]
+    notes: Vec<String>,
+    /// Minutes logged through `focus` sessions.
+    #[serde(default)]
+    time_spent_minutes: u32,
 }
 
 #[derive(Parser)]
@@ -44,51 +223,209 @@ struct Task {
     author = "Ali Mert"
 )]
 struct Cli {
+    #[command(flatten)]
+    global: GlobalOpts,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Args)]
+struct GlobalOpts {
+    /// Suppress success messages
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// Show what a command would change without saving it
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Log file operations to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Print tables as plain aligned text without borders or colors
+    #[arg(long, global = true)]
+    plain: bool,
+    /// Only accept dates as "%Y-%m-%d %H:%M", ignoring the configured date_format
+    #[arg(long, global = true)]
+    strict_date: bool,
+    /// Print JSON output on a single line instead of indented
+    #[arg(long, global = true)]
+    compact: bool,
+    /// Output format of list, show and stats (default table), export (default
+    /// json) and import (json or csv)
+    #[arg(short, long, global = true, value_enum)]
+    format: Option<OutputFormat>,
+}
+
+impl GlobalOpts {
+    fn info(&self, message: &str) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    fn warn(&self, message: &str) {
+        if !self.quiet {
+            eprintln!("{}", message.yellow());
+        }
+    }
+
+    /// The format for list, show and stats; `json` is their older `--json` flag.
+    fn output_format(&self, json: bool) -> Result<OutputFormat, String> {
+        match self.format {
+            Some(format) if json && format != OutputFormat::Json => Err(format!(
+                "--json can't be combined with --format {}",
+                format.name()
+            )),
+            _ if json => Ok(OutputFormat::Json),
+            format => Ok(format.unwrap_or_default()),
+        }
+    }
+
+    /// Whether task tables end with a count summary.
+    fn shows_summary(&self) -> bool {
+        !self.quiet && !self.plain
+    }
+
+    fn report(&self, message: &str, dry_run_message: &str) {
+        if self.dry_run {
+            println!("{}", dry_run_message);
+        } else {
+            self.info(message);
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     Add {
         title: String,
         #[arg(short, long)]
         description: Option<String>,
-        #[arg(short, long)]
+        #[arg(long)]
         due: Option<String>,
-        #[arg(short, long, default_value = "medium")]
-        priority: String,
-        #[arg(short, long, default_value = "personal")]
-        category: String,
+        /// Set the due date relative to now, e.g. 3d or 2h
+        #[arg(long, conflicts_with = "due")]
+        due_in: Option<String>,
         #[arg(short, long)]
-        tags: Option<String>,
-    },
-    List {
+        priority: Option<String>,
         #[arg(short, long)]
         category: Option<String>,
         #[arg(short, long)]
-        priority: Option<String>,
+        tags: Option<String>,
+        /// Add a single tag (repeatable, combined with --tags)
+        #[arg(long, value_parser = parse_single_tag)]
+        tag: Vec<String>,
+        /// Write the description in $EDITOR
+        #[arg(long, conflicts_with = "description")]
+        edit: bool,
+        /// ID of a task that must be completed first (repeatable)
+        #[arg(long)]
+        blocked_by: Vec<usize>,
+        /// Make this a subtask of the given task
+        #[arg(long)]
+        parent: Option<usize>,
+        /// Estimated effort, e.g. 30m or 2h
+        #[arg(long)]
+        estimate: Option<String>,
+        /// Add the task even if a pending task with the same title exists
         #[arg(short, long)]
+        yes: bool,
+    },
+    /// List tasks (pending only, unless --completed or --all is given)
+    List {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Show only completed tasks
+        #[arg(long, conflicts_with = "pending")]
         completed: bool,
-        #[arg(short, long)]
+        /// Show only pending tasks (the default)
+        #[arg(long)]
         pending: bool,
+        /// Show pending and completed tasks
+        #[arg(long, conflicts_with_all = ["completed", "pending"])]
+        all: bool,
+        #[arg(long)]
+        due_before: Option<String>,
+        #[arg(long)]
+        due_after: Option<String>,
+        #[arg(long)]
+        created_before: Option<String>,
+        #[arg(long)]
+        created_after: Option<String>,
+        /// Only tasks completed on or before this date (implies --completed)
+        #[arg(long, conflicts_with = "pending")]
+        completed_before: Option<String>,
+        /// Only tasks completed on or after this date (implies --completed)
+        #[arg(long, conflicts_with = "pending")]
+        completed_after: Option<String>,
+        /// Only tasks at or above this priority, e.g. high
+        #[arg(long, value_name = "LEVEL")]
+        min_priority: Option<String>,
+        /// Only tasks without a due date
+        #[arg(long, conflicts_with_all = ["has_due", "due_before", "due_after"])]
+        no_due: bool,
+        /// Only tasks with a due date
+        #[arg(long)]
+        has_due: bool,
+        #[arg(long, value_parser = ["category", "priority"])]
+        group_by: Option<String>,
+        /// Group into Overdue, Today, This Week, Later and No date sections
+        #[arg(long, conflicts_with_all = ["group_by", "tree"])]
+        by_date: bool,
+        #[arg(long, value_enum, value_delimiter = ',')]
+        columns: Vec<Column>,
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+        #[arg(long, requires = "sort")]
+        reverse: bool,
+        /// Newest first; shorthand for --sort created --reverse
+        #[arg(long, conflicts_with_all = ["sort", "reverse"])]
+        recent: bool,
+        /// Show due dates relative to now, e.g. "in 2 days"
+        #[arg(long)]
+        relative: bool,
+        /// Show tasks as a tree of subtasks instead of a table
+        #[arg(long, conflicts_with_all = ["group_by", "columns"])]
+        tree: bool,
+        /// Write the table to a file (without colors) instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Show the output through $PAGER (default "less -R")
+        #[arg(long, conflicts_with = "output")]
+        page: bool,
     },
     Show {
-        id: usize,
+        /// Task id, or part of its title
+        task: String,
+        #[arg(long)]
+        json: bool,
     },
     Complete {
-        id: usize,
+        /// Task ids, or parts of pending task titles
+        #[arg(conflicts_with_all = ["category", "priority", "tag"])]
+        ids: Vec<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[arg(short, long)]
+        yes: bool,
     },
     Remove {
-        id: usize,
+        #[arg(conflicts_with_all = ["category", "priority", "tag"])]
+        id: Option<usize>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[arg(short, long)]
+        yes: bool,
+        /// Allow removing more than 10 tasks at once
+        #[arg(long)]
+        force: bool,
     },
     Edit {
         id: usize,
-        #[arg(short, long)]
+        #[arg(long)]
         title: Option<String>,
         #[arg(short, long)]
         description: Option<String>,
-        #[arg(short, long)]
+        #[arg(long)]
         due: Option<String>,
         #[arg(short, long)]
         priority: Option<String>,
@@ -96,7 +433,312 @@ enum Commands {
         category: Option<String>,
         #[arg(short, long)]
         tags: Option<String>,
+        /// Add a single tag (repeatable, combined with --tags)
+        #[arg(long, value_parser = parse_single_tag)]
+        tag: Vec<String>,
+        /// Edit the description in $EDITOR
+        #[arg(long, conflicts_with = "description")]
+        edit: bool,
+        /// Show the changes and ask before saving them
+        #[arg(long)]
+        diff: bool,
+        /// Replace the task's blockers (repeatable)
+        #[arg(long)]
+        blocked_by: Vec<usize>,
+        /// Remove all of the task's blockers
+        #[arg(long, conflicts_with = "blocked_by")]
+        no_blocked_by: bool,
+        /// Move the task under the given parent task
+        #[arg(long)]
+        parent: Option<usize>,
+        /// Make the task top-level again
+        #[arg(long, conflicts_with = "parent")]
+        no_parent: bool,
+        /// Estimated effort, e.g. 30m or 2h
+        #[arg(long)]
+        estimate: Option<String>,
+    },
+    Archive,
+    Archived,
+    Bump {
+        id: usize,
+    },
+    Lower {
+        id: usize,
+    },
+    /// Append a timestamped note to a task
+    Note {
+        id: usize,
+        text: String,
+    },
+    /// Keep a task at the top of lists
+    Pin {
+        id: usize,
+    },
+    Unpin {
+        id: usize,
+    },
+    Repl,
+    Renumber,
+    Duplicate {
+        id: usize,
+    },
+    AddBatch,
+    /// Add a task by answering prompts for each field
+    AddInteractive,
+    InitConfig,
+    Export {
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(long, conflicts_with = "pending")]
+        completed: bool,
+        #[arg(long)]
+        pending: bool,
+        /// Only export tasks with an id greater than this
+        #[arg(long)]
+        since_id: Option<usize>,
+        /// Only export tasks with an id of at least this
+        #[arg(long)]
+        min_id: Option<usize>,
+        /// Only export tasks with an id of at most this
+        #[arg(long)]
+        max_id: Option<usize>,
+    },
+    #[cfg(feature = "notifications")]
+    Notify,
+    Progress,
+    Ids {
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[arg(long, conflicts_with = "pending")]
+        completed: bool,
+        #[arg(long)]
+        pending: bool,
+        #[arg(long)]
+        inline: bool,
+    },
+    Report {
+        since: String,
+    },
+    Snooze {
+        id: usize,
+        by: String,
+    },
+    Merge {
+        other: PathBuf,
+    },
+    Search {
+        query: String,
+        /// Rank titles by fuzzy match score instead of substring matching
+        #[arg(long)]
+        fuzzy: bool,
+        /// Match upper and lower case exactly
+        #[arg(long)]
+        case_sensitive: bool,
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+        #[arg(long, requires = "sort")]
+        reverse: bool,
+    },
+    /// Explain the priority colors and status symbols used in task tables
+    Legend,
+    /// Remove completed tasks finished more than the given duration ago (e.g. 30d)
+    Purge {
+        older_than: String,
+        #[arg(short, long)]
+        yes: bool,
+        /// Allow purging more than 10 tasks at once
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add every task from a JSON task file, giving each a new id
+    Import {
+        /// With --format csv, the file needs the columns `export --format csv` writes
+        file: PathBuf,
+        /// List the tasks that would be added without saving anything
+        #[arg(long)]
+        preview: bool,
+    },
+    /// Show pending tasks and redraw whenever the task file changes
+    #[cfg(feature = "watch")]
+    Watch {
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Encrypt the task file with a passphrase (or TODO_PASSPHRASE)
+    #[cfg(feature = "encrypt")]
+    SetPassword {
+        /// Store the task file unencrypted again
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Summarize task counts by status, priority and category
+    Stats {
+        /// Print the statistics as a JSON object
+        #[arg(long)]
+        json: bool,
+        /// Print only this metric, e.g. overdue or completion_percent
+        #[arg(long, conflicts_with = "json")]
+        field: Option<String>,
+    },
+    /// Change a task's title
+    Rename {
+        id: usize,
+        title: String,
+    },
+    /// Add a tag to every task matching the filters
+    TagAll {
+        #[arg(value_name = "TAG")]
+        name: String,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Rename a tag on every task that has it
+    RenameTag {
+        from: String,
+        to: String,
+    },
+    /// Move every task in one category to another
+    RenameCategory {
+        from: String,
+        to: String,
+    },
+    /// Move tasks from categories matching a selector such as "work,proj-*"
+    MoveCategory {
+        from: String,
+        to: String,
+        /// Leave completed tasks where they are
+        #[arg(long)]
+        pending: bool,
+    },
+    /// Show the most recently created task
+    Last,
+    /// Show pending tasks due today or overdue, with the planned workload
+    Today,
+    /// Show a task and count down a focus session, then offer to complete it
+    Focus {
+        id: usize,
+        /// Session length in minutes
+        #[arg(default_value_t = 25, value_parser = clap::value_parser!(u64).range(1..=1440))]
+        minutes: u64,
+    },
+    /// Save a reusable task skeleton; `{date}` in the title becomes today's date
+    SaveTemplate {
+        name: String,
+        #[arg(long)]
+        title: String,
+        #[arg(short, long)]
+        description: Option<String>,
+        #[arg(short, long)]
+        priority: Option<String>,
+        #[arg(short, long)]
+        category: Option<String>,
+        #[arg(short, long)]
+        tags: Option<String>,
+        /// Add a single tag (repeatable, combined with --tags)
+        #[arg(long, value_parser = parse_single_tag)]
+        tag: Vec<String>,
+        /// Estimated effort, e.g. 30m or 2h
+        #[arg(long)]
+        estimate: Option<String>,
+    },
+    /// Add a task from a saved template
+    AddFromTemplate {
+        name: String,
+        /// Use this title instead of the template's
+        title: Option<String>,
     },
+    /// List saved templates
+    Templates,
+}
+
+/// Values of the global `--format`. `table` is only for `list`, `show` and
+/// `stats`, which have no use for `ics` and `html`; export takes all others.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    Jsonl,
+    Markdown,
+    Ics,
+    Html,
+}
+
+impl OutputFormat {
+    fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Ics => "ics",
+            OutputFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Column {
+    Id,
+    Status,
+    Title,
+    Due,
+    Priority,
+    Category,
+    Tags,
+}
+
+impl Column {
+    const ALL: [Column; 7] = [
+        Column::Id,
+        Column::Status,
+        Column::Title,
+        Column::Due,
+        Column::Priority,
+        Column::Category,
+        Column::Tags,
+    ];
+
+    fn header(self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Status => "Status",
+            Column::Title => "Title",
+            Column::Due => "Due Date",
+            Column::Priority => "Priority",
+            Column::Category => "Category",
+            Column::Tags => "Tags",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Id,
+    Priority,
+    Due,
+    Created,
+    Title,
+    Tags,
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    #[arg(short, long)]
+    category: Vec<String>,
+    #[arg(short, long)]
+    priority: Vec<String>,
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+impl FilterArgs {
+    fn is_empty(&self) -> bool {
+        self.category.is_empty() && self.priority.is_empty() && self.tag.is_none()
+    }
 }
 
 impl Task {
@@ -109,231 +751,1611 @@ impl Task {
         category: &str,
         tags: Option<String>,
     ) -> Result<Self, String> {
-        let priority = match priority.to_lowercase().as_str() {
-            "low" => Priority::Low,
-            "medium" => Priority::Medium,
-            "high" => Priority::High,
-            "critical" => Priority::Critical,
-            _ => return Err("Invalid priority level".to_string()),
-        };
+        validate_title(&title)?;
 
-        let category = match category.to_lowercase().as_str() {
-            "personal" => Category::Personal,
-            "work" => Category::Work,
-            "shopping" => Category::Shopping,
-            "health" => Category::Health,
-            other => Category::Other(other.to_string()),
-        };
+        let priority = Priority::parse(priority)?;
 
-        let due_date = if let Some(date_str) = due_date {
-            Some(
-                NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M")
-                    .map_err(|e| format!("Invalid date format: {}", e))?,
-            )
-        } else {
-            None
-        };
+        let category = Category::parse(category)?;
+
+        let due_date = due_date.as_deref().map(parse_due_date).transpose()?;
 
-        let tags = tags
-            .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
-            .unwrap_or_default();
+        let tags = tags.as_deref().map(parse_tags).unwrap_or_default();
 
         Ok(Task {
             id,
             title,
             description,
             completed: false,
+            completed_at: None,
             created_at: Local::now(),
             due_date,
             priority,
             category,
             tags,
+            blocked_by: Vec::new(),
+            parent: None,
+            estimate: None,
+            pinned: false,
+            notes: Vec::new(),
+            time_spent_minutes: 0,
         })
     }
 
-    fn to_row(&self) -> Row {
-        let status = if self.completed {
-            "✓".green()
+    /// True for completed tasks finished before `cutoff`. Tasks completed before
+    /// `completed_at` was recorded never match.
+    fn completed_before(&self, cutoff: DateTime<Local>) -> bool {
+        self.completed && self.completed_at.is_some_and(|at| at < cutoff)
+    }
+
+    /// True for pending tasks due within the next 24 hours.
+    fn is_due_soon(&self) -> bool {
+        let now = Local::now().naive_local();
+        !self.completed
+            && self
+                .due_date
+                .is_some_and(|d| d >= now && d <= now + Duration::hours(24))
+    }
+
+    /// True for pending tasks due later today.
+    fn is_due_today(&self) -> bool {
+        let now = Local::now().naive_local();
+        !self.completed
+            && self
+                .due_date
+                .is_some_and(|d| d >= now && d.date() == now.date())
+    }
+
+    fn is_due_in_past(&self) -> bool {
+        self.due_date
+            .map(|d| d < Local::now().naive_local())
+            .unwrap_or(false)
+    }
+
+    fn is_overdue(&self) -> bool {
+        !self.completed && self.is_due_in_past()
+    }
+
+    fn to_row(&self, columns: &[Column], blocked: bool, relative: bool) -> Row {
+        let status = if blocked && !self.completed {
+            blocked_symbol()
+        } else if self.is_due_today() {
+            due_today_symbol()
         } else {
-            "✗".red()
+            status_symbol(self.completed)
         };
-
-        let priority_color = match self.priority {
-            Priority::Low => "Low".normal(),
-            Priority::Medium => "Medium".yellow(),
-            Priority::High => "High".bright_red(),
-            Priority::Critical => "Critical".red().bold(),
+        let status = if self.pinned {
+            format!("📌 {}", status)
+        } else {
+            status.to_string()
         };
+        let priority_color = if config().escalate_near_due && self.is_due_soon() {
+            self.priority
+                .raised()
+                .style(&format!("{:?}", self.priority))
+        } else {
+            self.priority.colored()
+        };
+        let category_str = self.category.name();
 
-        let category_str = match &self.category {
-            Category::Personal => "Personal",
-            Category::Work => "Work",
-            Category::Shopping => "Shopping",
-            Category::Health => "Health",
-            Category::Other(s) => s,
+        let due_date = match self.due_date {
+            Some(d) => {
+                let formatted = if relative {
+                    relative_due(d, self.completed)
+                } else {
+                    d.format(config().date_format()).to_string()
+                };
+                if self.is_overdue() {
+                    formatted.red().to_string()
+                } else {
+                    formatted
+                }
+            }
+            None => "-".to_string(),
         };
 
-        let due_date = self
-            .due_date
-            .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
-            .unwrap_or_else(|| "-".to_string());
+        Row::new(
+            columns
+                .iter()
+                .map(|column| match column {
+                    Column::Id => Cell::new(&self.id.to_string()),
+                    Column::Status => Cell::new(&status),
+                    Column::Title => Cell::new(&self.title),
+                    Column::Due => Cell::new(&due_date),
+                    Column::Priority => Cell::new(&priority_color.to_string()),
+                    Column::Category => Cell::new(category_str),
+                    Column::Tags => Cell::new(&colored_tags(&self.tags)),
+                })
+                .collect(),
+        )
+    }
+}
+
+const CONFIG_TEMPLATE: &str = r#"# Configuration for todo. Uncomment a setting to override the built-in default.
+# Command-line flags always take precedence over these values.
+
+# Priority used by `add` when --priority isn't given: low, medium, high or critical.
+# default_priority = "medium"
+
+# Category used by `add` when --category isn't given.
+# default_category = "personal"
+
+# chrono format used to parse and display dates, e.g. "%d/%m/%Y %H:%M".
+# date_format = "%Y-%m-%d %H:%M"
+
+# Set to false to disable colored output.
+# color = true
+
+# Give tasks with duplicate ids fresh unique ids on load instead of warning.
+# fix_duplicate_ids = false
+
+# Color pending tasks due within 24 hours as one priority level higher in lists.
+# The stored priority doesn't change.
+# escalate_near_due = false
+
+# Move tasks to the archive as soon as they are completed, so the main list
+# only holds pending tasks. Use `archived` to see them.
+# archive_on_complete = false
+
+# Warn and ask for confirmation when `add` is given the title of a pending task.
+# warn_duplicates = true
+
+# Colors for individual tags in task lists, e.g. "red" or "bright blue".
+# Tags not listed here use the default color.
+# [tag_colors]
+# urgent = "red"
+"#;
+
+const FUZZY_SEARCH_LIMIT: usize = 10;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+static PLAIN_OUTPUT: AtomicBool = AtomicBool::new(false);
 
-        Row::new(vec![
-            Cell::new(&self.id.to_string()),
-            Cell::new(&status.to_string()),
-            Cell::new(&self.title),
-            Cell::new(&due_date),
-            Cell::new(&priority_color.to_string()),
-            Cell::new(category_str),
-            Cell::new(&self.tags.join(", ")),
-        ])
+/// Set by `--strict-date`.
+static STRICT_DATES: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--compact`.
+static COMPACT_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Serializes JSON output for display or export, indented unless `--compact`
+/// is given. The task file itself is always written indented.
+fn json_string<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    if COMPACT_JSON.load(atomic::Ordering::Relaxed) {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
     }
 }
 
-struct TodoManager {
-    tasks: Vec<Task>,
-    file_path: PathBuf,
+/// Switches between regular and `--plain` output, and turns colors off when
+/// either `--plain` or the config asks for it.
+fn apply_output_mode(plain: bool) {
+    PLAIN_OUTPUT.store(plain, atomic::Ordering::Relaxed);
+    if plain || config().color == Some(false) {
+        colored::control::set_override(false);
+    } else {
+        colored::control::unset_override();
+    }
 }
 
-impl TodoManager {
-    fn new() -> Self {
-        let file_path = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".todo-cli.json");
-        
-        let tasks = if file_path.exists() {
-            let contents = fs::read_to_string(&file_path).unwrap_or_else(|_| "[]".to_string());
-            serde_json::from_str(&contents).unwrap_or_else(|_| Vec::new())
-        } else {
-            Vec::new()
+fn plain_output() -> bool {
+    PLAIN_OUTPUT.load(atomic::Ordering::Relaxed)
+}
+
+/// A table in the default bordered style, or borderless columns under `--plain`.
+fn new_table() -> Table {
+    let mut table = Table::new();
+    if plain_output() {
+        table.set_format(FormatBuilder::new().padding(0, 2).build());
+    }
+    table
+}
+
+/// Prints a two-column table, or `key: value` lines under `--plain`.
+fn print_key_values(table: &Table) {
+    if !plain_output() {
+        table.printstd();
+        return;
+    }
+    for row in table.row_iter() {
+        let cell = |index| {
+            row.get_cell(index)
+                .map(Cell::get_content)
+                .unwrap_or_default()
         };
+        println!("{}: {}", cell(0), cell(1));
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Config {
+    default_priority: Option<String>,
+    default_category: Option<String>,
+    date_format: Option<String>,
+    color: Option<bool>,
+    fix_duplicate_ids: bool,
+    escalate_near_due: bool,
+    archive_on_complete: bool,
+    warn_duplicates: Option<bool>,
+    tag_colors: HashMap<String, String>,
+}
 
-        TodoManager { tasks, file_path }
+impl Config {
+    fn path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".todo-cli.toml")
     }
 
-    fn save(&self) -> Result<(), String> {
-        let contents = serde_json::to_string_pretty(&self.tasks)
-            .map_err(|e| format!("Failed to serialize tasks: {}", e))?;
-        fs::write(&self.file_path, contents)
-            .map_err(|e| format!("Failed to save tasks: {}", e))?;
-        Ok(())
+    fn load() -> Result<Self, String> {
+        let path = Config::path();
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        for (tag, color) in &config.tag_colors {
+            if color.parse::<Color>().is_err() {
+                return Err(format!(
+                    "Unknown color '{}' for tag '{}' in {}",
+                    color,
+                    tag,
+                    path.display()
+                ));
+            }
+        }
+        Ok(config)
     }
 
-    fn add_task(&mut self, task: Task) -> Result<(), String> {
-        self.tasks.push(task);
-        self.save()?;
-        Ok(())
+    fn default_priority(&self) -> &str {
+        self.default_priority.as_deref().unwrap_or("medium")
     }
 
-    fn list_tasks(&self, filters: &ListFilters) -> Vec<&Task> {
+    fn default_category(&self) -> &str {
+        self.default_category.as_deref().unwrap_or("personal")
+    }
+
+    fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+
+    fn warn_duplicates(&self) -> bool {
+        self.warn_duplicates.unwrap_or(true)
+    }
+
+    fn tag_color(&self, tag: &str) -> Option<Color> {
+        self.tag_colors
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(tag))
+            .and_then(|(_, color)| color.parse().ok())
+    }
+}
+
+fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+struct TodoManager {
+    tasks: Vec<Task>,
+    file_path: PathBuf,
+    dry_run: bool,
+    verbose: bool,
+    /// Set when the JSON task file is encrypted; saves re-encrypt with it.
+    #[cfg(feature = "encrypt")]
+    passphrase: Option<String>,
+    #[cfg(feature = "sqlite")]
+    store: sqlite::SqliteStore,
+    /// Lower bound for the next new id, persisted so ids are never reused.
+    next_id: usize,
+}
+
+/// Overrides where tasks are stored.
+const TASK_FILE_ENV: &str = "TODO_FILE";
+
+/// `TODO_FILE` if set, else `~/.todo-cli.json`. Without a home directory the
+/// file lives in the current directory, which depends on where the command is
+/// run, so that fallback comes with a warning.
+fn task_file_path(todo_file: Option<std::ffi::OsString>, home: Option<PathBuf>) -> PathBuf {
+    if let Some(path) = todo_file.filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    if let Some(home) = home {
+        return home.join(".todo-cli.json");
+    }
+    let path = env::current_dir()
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(".todo-cli.json");
+    eprintln!(
+        "{}",
+        format!(
+            "Warning: couldn't find your home directory, using {}. Set {} to keep tasks in one place.",
+            path.display(),
+            TASK_FILE_ENV
+        )
+        .yellow()
+    );
+    path
+}
+
+impl TodoManager {
+    fn new(dry_run: bool, verbose: bool) -> Result<Self, String> {
+        let file_path = task_file_path(env::var_os(TASK_FILE_ENV), dirs::home_dir());
+
+        #[cfg(not(feature = "sqlite"))]
+        let storage_path = file_path.clone();
+        #[cfg(feature = "sqlite")]
+        let storage_path = file_path.with_extension("db");
+        if verbose {
+            eprintln!("Using task storage at {}", storage_path.display());
+        }
+
+        #[cfg(all(not(feature = "sqlite"), not(feature = "encrypt")))]
+        let file = if file_path.exists() {
+            load_task_file(&file_path)?
+        } else {
+            TaskFile::empty()
+        };
+        #[cfg(all(not(feature = "sqlite"), feature = "encrypt"))]
+        let (file, passphrase) = if file_path.exists() {
+            crypto::read_task_file(&file_path, None)?
+        } else {
+            (TaskFile::empty(), None)
+        };
+        #[cfg(not(feature = "sqlite"))]
+        let TaskFile {
+            version,
+            next_id,
+            tasks,
+        } = file;
+        #[cfg(all(feature = "sqlite", feature = "encrypt"))]
+        let passphrase = None;
+
+        #[cfg(feature = "sqlite")]
+        let store = sqlite::SqliteStore::open(&storage_path, &file_path)?;
+        #[cfg(feature = "sqlite")]
+        let tasks = store.load()?;
+        #[cfg(feature = "sqlite")]
+        let next_id = store.load_next_id()?;
+        // The database stores tasks individually, so it has no file-level version.
+        #[cfg(feature = "sqlite")]
+        let version = SCHEMA_VERSION;
+
+        if verbose {
+            eprintln!(
+                "Loaded {} tasks from {}",
+                tasks.len(),
+                storage_path.display()
+            );
+        }
+
+        let mut manager = TodoManager {
+            tasks,
+            file_path,
+            dry_run,
+            verbose,
+            #[cfg(feature = "encrypt")]
+            passphrase,
+            #[cfg(feature = "sqlite")]
+            store,
+            next_id,
+        };
+        manager.normalize_categories();
+        manager.normalize_tags();
+        if version < SCHEMA_VERSION {
+            // Files before version 3 had no id counter; archived tasks may hold
+            // ids above any left in the main list.
+            if let Ok(archive) = manager.load_archive() {
+                let highest = archive.iter().map(|t| t.id).max().unwrap_or(0);
+                manager.next_id = manager.next_id.max(highest + 1);
+            }
+            if verbose {
+                eprintln!(
+                    "Migrating {} from format version {} to {}",
+                    manager.file_path.display(),
+                    version,
+                    SCHEMA_VERSION
+                );
+            }
+            manager.save()?;
+        }
+        manager.check_duplicate_ids();
+        Ok(manager)
+    }
+
+    /// Older versions kept custom categories in whatever case they were typed.
+    fn normalize_categories(&mut self) {
+        for task in self.tasks.iter_mut() {
+            if let Category::Other(name) = &task.category {
+                if let Ok(category) = Category::parse(name) {
+                    task.category = category;
+                }
+            }
+        }
+    }
+
+    /// Lowercases and dedups tags saved before tags were normalized on input,
+    /// so that tag lookups can compare them directly.
+    fn normalize_tags(&mut self) {
+        for task in self.tasks.iter_mut() {
+            let mut tags: Vec<String> = Vec::with_capacity(task.tags.len());
+            for tag in task.tags.drain(..) {
+                let tag = tag.trim().to_lowercase();
+                if !tag.is_empty() && !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            task.tags = tags;
+        }
+    }
+
+    fn check_duplicate_ids(&mut self) {
+        let mut seen = HashSet::new();
+        let duplicates: Vec<usize> = self
+            .tasks
+            .iter()
+            .map(|t| t.id)
+            .filter(|id| !seen.insert(*id))
+            .collect();
+        if duplicates.is_empty() {
+            return;
+        }
+
+        if !config().fix_duplicate_ids {
+            eprintln!(
+                "{}",
+                format!(
+                    "Warning: found duplicate task ids ({}); commands may act on the wrong task. Run `todo renumber` to fix them.",
+                    duplicates.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+                )
+                .yellow()
+            );
+            return;
+        }
+
+        let mut next_id = self.next_id();
+        let mut seen = HashSet::new();
+        for task in self.tasks.iter_mut() {
+            if !seen.insert(task.id) {
+                eprintln!(
+                    "{}",
+                    format!("Reassigned duplicate task id {} to {}", task.id, next_id).yellow()
+                );
+                task.id = next_id;
+                seen.insert(next_id);
+                next_id += 1;
+            }
+        }
+        self.next_id = next_id;
+        if let Err(e) = self.save() {
+            eprintln!("Error saving fixed task ids: {}", e);
+        }
+    }
+
+    /// The file tasks are actually stored in: the JSON file, or the database
+    /// next to it when built with the `sqlite` feature.
+    fn storage_path(&self) -> PathBuf {
+        #[cfg(feature = "sqlite")]
+        return self.file_path.with_extension("db");
+
+        #[cfg(not(feature = "sqlite"))]
+        self.file_path.clone()
+    }
+
+    /// Re-reads tasks from storage, picking up changes made by other processes.
+    #[cfg(feature = "watch")]
+    fn reload(&mut self) -> Result<(), String> {
+        #[cfg(feature = "sqlite")]
+        {
+            self.tasks = self.store.load()?;
+        }
+        #[cfg(all(not(feature = "sqlite"), not(feature = "encrypt")))]
+        {
+            self.tasks = if self.file_path.exists() {
+                read_task_file(&self.file_path)?
+            } else {
+                Vec::new()
+            };
+        }
+        #[cfg(all(not(feature = "sqlite"), feature = "encrypt"))]
+        if self.file_path.exists() {
+            let file;
+            (file, self.passphrase) =
+                crypto::read_task_file(&self.file_path, self.passphrase.take())?;
+            self.tasks = file.tasks;
+        } else {
+            self.tasks = Vec::new();
+        }
+        self.normalize_categories();
+        self.normalize_tags();
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if self.dry_run {
+            return Ok(());
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            self.store.save(&self.tasks, self.next_id())?;
+            let bytes = fs::metadata(self.storage_path())
+                .map(|m| m.len())
+                .unwrap_or(0);
+            self.log_save(bytes);
+            Ok(())
+        }
+
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let contents = serialize_tasks(&self.tasks, self.next_id())?;
+            #[cfg(feature = "encrypt")]
+            let contents = match &self.passphrase {
+                Some(passphrase) => crypto::encrypt(contents.as_bytes(), passphrase)?,
+                None => contents.into_bytes(),
+            };
+            fs::write(&self.file_path, &contents)
+                .map_err(|e| format!("Failed to save tasks: {}", e))?;
+            self.log_save(contents.len() as u64);
+            Ok(())
+        }
+    }
+
+    fn log_save(&self, bytes: u64) {
+        if self.verbose {
+            eprintln!(
+                "Saved {} tasks to {} ({} bytes)",
+                self.tasks.len(),
+                self.storage_path().display(),
+                bytes
+            );
+        }
+    }
+
+    fn archive_path(&self) -> PathBuf {
+        self.file_path.with_extension("archive.json")
+    }
+
+    fn templates_path(&self) -> PathBuf {
+        self.file_path.with_extension("templates.json")
+    }
+
+    fn load_archive(&self) -> Result<Vec<Task>, String> {
+        let archive_path = self.archive_path();
+        if !archive_path.exists() {
+            return Ok(Vec::new());
+        }
+        #[cfg(not(feature = "encrypt"))]
+        let tasks = read_task_file(&archive_path)?;
+        #[cfg(feature = "encrypt")]
+        let tasks = crypto::read_task_file(&archive_path, self.passphrase.clone())?
+            .0
+            .tasks;
+        Ok(tasks)
+    }
+
+    /// Writes the archive, encrypted with the task file's passphrase if it has one.
+    fn save_archive(&self, archive: &[Task]) -> Result<(), String> {
+        if self.dry_run {
+            return Ok(());
+        }
+        let contents = serialize_tasks(archive, self.next_id())?;
+        #[cfg(feature = "encrypt")]
+        let contents = match &self.passphrase {
+            Some(passphrase) => crypto::encrypt(contents.as_bytes(), passphrase)?,
+            None => contents.into_bytes(),
+        };
+        fs::write(self.archive_path(), &contents)
+            .map_err(|e| format!("Failed to save archive: {}", e))?;
+        if self.verbose {
+            eprintln!(
+                "Saved {} tasks to {} ({} bytes)",
+                archive.len(),
+                self.archive_path().display(),
+                contents.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn archive_completed(&mut self) -> Result<usize, String> {
+        let mut archive = self.load_archive()?;
+        let (completed, pending): (Vec<Task>, Vec<Task>) =
+            self.tasks.drain(..).partition(|t| t.completed);
+        let count = completed.len();
+        archive.extend(completed);
+        self.tasks = pending;
+        self.save_archive(&archive)?;
+        self.save()?;
+        Ok(count)
+    }
+
+    /// The id for the next new task. Ids are never handed out twice, even
+    /// after the task that had one is removed or archived.
+    fn next_id(&self) -> usize {
+        let highest = self.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        self.next_id.max(highest + 1)
+    }
+
+    fn add_task(&mut self, task: Task) -> Result<(), String> {
+        self.next_id = self.next_id.max(task.id + 1);
+        self.tasks.push(task);
+        self.save()?;
+        Ok(())
+    }
+
+    fn add_tasks(&mut self, tasks: Vec<Task>) -> Result<(), String> {
+        let highest = tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        self.next_id = self.next_id.max(highest + 1);
+        self.tasks.extend(tasks);
+        self.save()?;
+        Ok(())
+    }
+
+    fn validate_blockers(&self, id: usize, blocked_by: &[usize]) -> Result<(), String> {
+        for &blocker in blocked_by {
+            if blocker == id {
+                return Err(format!("Task {} can't block itself", id));
+            }
+            if !self.tasks.iter().any(|t| t.id == blocker) {
+                return Err(format!("Blocking task with ID {} not found", blocker));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `parent` exists and isn't `id` itself or one of its subtasks.
+    fn validate_parent(&self, id: usize, parent: usize) -> Result<(), String> {
+        let mut current = Some(parent);
+        while let Some(ancestor) = current {
+            if ancestor == id {
+                return Err(format!("Task {} can't be its own parent or subtask", id));
+            }
+            current = self
+                .tasks
+                .iter()
+                .find(|t| t.id == ancestor)
+                .ok_or_else(|| format!("Parent task with ID {} not found", ancestor))?
+                .parent;
+        }
+        Ok(())
+    }
+
+    /// IDs of pending tasks waiting on at least one blocker that is still pending.
+    /// Blockers that have been removed or archived no longer count.
+    fn blocked_ids(&self) -> HashSet<usize> {
+        let pending: HashSet<usize> = self
+            .tasks
+            .iter()
+            .filter(|t| !t.completed)
+            .map(|t| t.id)
+            .collect();
         self.tasks
             .iter()
-            .filter(|task| {
-                let category_match = filters
-                    .category
-                    .as_ref()
-                    .map(|c| match &task.category {
-                        Category::Other(s) => s == c,
-                        _ => c == &format!("{:?}", task.category).to_lowercase(),
-                    })
-                    .unwrap_or(true);
-
-                let priority_match = filters
-                    .priority
-                    .as_ref()
-                    .map(|p| format!("{:?}", task.priority).to_lowercase() == *p)
-                    .unwrap_or(true);
-
-                let completion_match = if filters.completed {
-                    task.completed
-                } else if filters.pending {
-                    !task.completed
-                } else {
-                    true
-                };
+            .filter(|t| !t.completed && t.blocked_by.iter().any(|b| pending.contains(b)))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    fn list_tasks(&self, filters: &ListFilters) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| filters.matches(task))
+            .collect()
+    }
+
+    fn complete_task(&mut self, id: usize) -> Result<(), String> {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            // Completing again would move completed_at, which purge and report rely on.
+            if task.completed {
+                return Err(format!("Task {} is already completed", id));
+            }
+            task.completed = true;
+            task.completed_at = Some(Local::now());
+            self.save_completed()?;
+            Ok(())
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn complete_matching(&mut self, filters: &ListFilters) -> Result<usize, String> {
+        let now = Local::now();
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|t| filters.matches(t)) {
+            task.completed = true;
+            task.completed_at = Some(now);
+            count += 1;
+        }
+        self.save_completed()?;
+        Ok(count)
+    }
+
+    /// Saves after completing tasks, archiving them first when the config
+    /// asks for `archive_on_complete`.
+    fn save_completed(&mut self) -> Result<(), String> {
+        if config().archive_on_complete {
+            self.archive_completed().map(|_| ())
+        } else {
+            self.save()
+        }
+    }
+
+    fn remove_task(&mut self, id: usize) -> Result<Task, String> {
+        if let Some(index) = self.tasks.iter().position(|t| t.id == id) {
+            let task = self.tasks.remove(index);
+            self.save()?;
+            Ok(task)
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn purgeable(&self, cutoff: DateTime<Local>) -> impl Iterator<Item = &Task> {
+        self.tasks
+            .iter()
+            .filter(move |t| t.completed_before(cutoff))
+    }
+
+    fn purge_completed(&mut self, cutoff: DateTime<Local>) -> Result<Vec<Task>, String> {
+        let (purged, kept): (Vec<Task>, Vec<Task>) = self
+            .tasks
+            .drain(..)
+            .partition(|t| t.completed_before(cutoff));
+        self.tasks = kept;
+        if !purged.is_empty() {
+            self.save()?;
+        }
+        Ok(purged)
+    }
+
+    fn remove_matching(&mut self, filters: &ListFilters) -> Result<Vec<Task>, String> {
+        let (removed, kept): (Vec<Task>, Vec<Task>) =
+            self.tasks.drain(..).partition(|t| filters.matches(t));
+        self.tasks = kept;
+        self.save()?;
+        Ok(removed)
+    }
+
+    fn edit_task(&mut self, id: usize, updates: TaskUpdates) -> Result<(), String> {
+        let edited = self.preview_edit(id, updates)?;
+        self.replace_task(edited)
+    }
+
+    /// Returns a copy of task `id` with `updates` applied, without changing the
+    /// stored task.
+    fn preview_edit(&self, id: usize, updates: TaskUpdates) -> Result<Task, String> {
+        if let Some(blocked_by) = &updates.blocked_by {
+            self.validate_blockers(id, blocked_by)?;
+        }
+        if let Some(Some(parent)) = updates.parent {
+            self.validate_parent(id, parent)?;
+        }
+        let mut task = self.show_task(id)?.clone();
+        if let Some(title) = updates.title {
+            validate_title(&title)?;
+            task.title = title;
+        }
+        if let Some(description) = updates.description {
+            task.description = Some(description);
+        }
+        if let Some(due_date) = updates.due {
+            task.due_date = Some(parse_due_date(&due_date)?);
+        }
+        if let Some(priority) = updates.priority {
+            task.priority = Priority::parse(&priority)?;
+        }
+        if let Some(category) = updates.category {
+            task.category = Category::parse(&category)?;
+        }
+        if let Some(tags) = updates.tags {
+            task.tags = parse_tags(&tags);
+        }
+        if let Some(blocked_by) = updates.blocked_by {
+            task.blocked_by = blocked_by;
+        }
+        if let Some(parent) = updates.parent {
+            task.parent = parent;
+        }
+        if let Some(estimate) = updates.estimate {
+            task.estimate = Some(parse_estimate(&estimate)?);
+        }
+        Ok(task)
+    }
+
+    fn replace_task(&mut self, task: Task) -> Result<(), String> {
+        match self.tasks.iter_mut().find(|t| t.id == task.id) {
+            Some(existing) => *existing = task,
+            None => return Err(format!("Task with ID {} not found", task.id)),
+        }
+        self.save()
+    }
+
+    fn set_priority_with(
+        &mut self,
+        id: usize,
+        change: fn(Priority) -> Priority,
+    ) -> Result<Priority, String> {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.priority = change(task.priority);
+            let priority = task.priority;
+            self.save()?;
+            Ok(priority)
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn add_note(&mut self, id: usize, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Note cannot be empty".to_string());
+        }
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            let now = Local::now().format(config().date_format());
+            task.notes.push(format!("[{}] {}", now, text));
+            self.save()
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn add_time_spent(&mut self, id: usize, minutes: u32) -> Result<u32, String> {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.time_spent_minutes = task.time_spent_minutes.saturating_add(minutes);
+            let total = task.time_spent_minutes;
+            self.save()?;
+            Ok(total)
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn set_pinned(&mut self, id: usize, pinned: bool) -> Result<(), String> {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.pinned = pinned;
+            self.save()
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    fn duplicate_task(&mut self, id: usize) -> Result<usize, String> {
+        let original = self.show_task(id)?;
+        let copy = Task {
+            id: self.next_id(),
+            title: original.title.clone(),
+            description: original.description.clone(),
+            completed: false,
+            completed_at: None,
+            created_at: Local::now(),
+            due_date: original.due_date,
+            priority: original.priority,
+            category: original.category.clone(),
+            tags: original.tags.clone(),
+            blocked_by: original.blocked_by.clone(),
+            parent: original.parent,
+            estimate: original.estimate,
+            pinned: false,
+            notes: Vec::new(),
+            time_spent_minutes: 0,
+        };
+        let new_id = copy.id;
+        self.add_task(copy)?;
+        Ok(new_id)
+    }
+
+    fn snooze_task(&mut self, id: usize, by: Duration) -> Result<NaiveDateTime, String> {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            let base = task
+                .due_date
+                .unwrap_or_else(|| truncate_to_minute(Local::now().naive_local()));
+            let due_date = base
+                .checked_add_signed(by)
+                .ok_or_else(|| "New due date is out of range".to_string())?;
+            task.due_date = Some(due_date);
+            self.save()?;
+            Ok(due_date)
+        } else {
+            Err(format!("Task with ID {} not found", id))
+        }
+    }
+
+    /// Appends tasks from `other` that aren't already present, where tasks are
+    /// considered the same when title, due date and category all match. Returns
+    /// the number of tasks added and skipped.
+    fn merge_tasks(&mut self, other: Vec<Task>) -> Result<(usize, usize), String> {
+        let mut next_id = self.next_id();
+        let mut added = 0;
+        let mut skipped = 0;
+        for mut task in other {
+            let duplicate = self.tasks.iter().any(|t| {
+                t.title == task.title
+                    && t.due_date == task.due_date
+                    && t.category.name() == task.category.name()
+            });
+            if duplicate {
+                skipped += 1;
+                continue;
+            }
+            task.id = next_id;
+            next_id += 1;
+            // Blocker and parent IDs refer to the other file's numbering, so they can't be kept.
+            task.blocked_by.clear();
+            task.parent = None;
+            self.tasks.push(task);
+            added += 1;
+        }
+        self.next_id = next_id;
+        if added > 0 {
+            self.save()?;
+        }
+        Ok((added, skipped))
+    }
+
+    /// Pairs each incoming task's old id with the id it would get on import.
+    fn import_plan(&self, tasks: &[Task]) -> Vec<(usize, usize)> {
+        let first_id = self.next_id();
+        tasks
+            .iter()
+            .enumerate()
+            .map(|(offset, t)| (t.id, first_id + offset))
+            .collect()
+    }
+
+    fn import_tasks(&mut self, mut tasks: Vec<Task>) -> Result<Vec<(usize, usize)>, String> {
+        let plan = self.import_plan(&tasks);
+        let mut remapped = HashMap::new();
+        for &(old, new) in &plan {
+            remapped.entry(old).or_insert(new);
+        }
+        for (task, &(_, new_id)) in tasks.iter_mut().zip(&plan) {
+            task.id = new_id;
+            // Links to tasks outside the imported file can't be resolved, so they're dropped.
+            task.blocked_by = task
+                .blocked_by
+                .iter()
+                .filter_map(|b| remapped.get(b).copied())
+                .collect();
+            task.parent = task.parent.and_then(|p| remapped.get(&p).copied());
+        }
+        self.add_tasks(tasks)?;
+        Ok(plan)
+    }
+
+    /// Adds `tag` to every matching task that doesn't have it yet and returns
+    /// how many tasks changed.
+    fn tag_matching(&mut self, tag: &str, filters: &ListFilters) -> Result<usize, String> {
+        let tag = match parse_tags(tag).as_slice() {
+            [tag] => tag.clone(),
+            _ => return Err("Tag must be a single non-empty tag".to_string()),
+        };
+
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|t| filters.matches(t)) {
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag.clone());
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.save()?;
+        }
+        Ok(count)
+    }
+
+    /// Replaces tag `from` with `to` on every task, matching case-insensitively
+    /// and dropping the duplicate when a task already has `to`. Returns the
+    /// number of tasks changed.
+    fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, String> {
+        let from = from.trim().to_lowercase();
+        let to = match parse_tags(to).as_slice() {
+            [tag] => tag.clone(),
+            _ => return Err("New tag name must be a single non-empty tag".to_string()),
+        };
+
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|t| t.tags.contains(&from)) {
+            let renamed = task
+                .tags
+                .iter()
+                .map(|tag| {
+                    if *tag == from {
+                        to.as_str()
+                    } else {
+                        tag.as_str()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            task.tags = parse_tags(&renamed);
+            count += 1;
+        }
+        if count > 0 {
+            self.save()?;
+        }
+        Ok(count)
+    }
+
+    /// Moves every task in category `from` to `to`, returning how many changed.
+    fn rename_category(&mut self, from: &str, to: &str) -> Result<usize, String> {
+        let to = Category::parse(to)?;
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|t| t.category.matches(from)) {
+            task.category = to.clone();
+            count += 1;
+        }
+        if count > 0 {
+            self.save()?;
+        }
+        Ok(count)
+    }
+
+    /// Moves tasks whose category matches `selector` (see
+    /// `Category::matches_selector`) to `to`, optionally only pending ones.
+    /// Tasks already in `to` aren't counted.
+    fn move_category(
+        &mut self,
+        selector: &str,
+        to: &str,
+        pending_only: bool,
+    ) -> Result<usize, String> {
+        let to = Category::parse(to)?;
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|t| {
+            t.category.matches_selector(selector)
+                && !t.category.matches(to.name())
+                && !(pending_only && t.completed)
+        }) {
+            task.category = to.clone();
+            count += 1;
+        }
+        if count > 0 {
+            self.save()?;
+        }
+        Ok(count)
+    }
 
-                category_match && priority_match && completion_match
+    /// Gives tasks contiguous ids starting at 1, in list order. Ids held by
+    /// archived tasks are skipped so the two files never share an id.
+    fn renumber(&mut self) -> Result<Vec<(usize, usize)>, String> {
+        let archived: HashSet<usize> = self.load_archive()?.iter().map(|t| t.id).collect();
+        let mut changes = Vec::new();
+        let mut new_id = 0;
+        for task in self.tasks.iter_mut() {
+            new_id += 1;
+            while archived.contains(&new_id) {
+                new_id += 1;
+            }
+            if task.id != new_id {
+                changes.push((task.id, new_id));
+                task.id = new_id;
+            }
+        }
+        if !changes.is_empty() {
+            let mut remapped = HashMap::new();
+            for &(old, new) in &changes {
+                remapped.entry(old).or_insert(new);
+            }
+            for task in self.tasks.iter_mut() {
+                for blocker in task.blocked_by.iter_mut() {
+                    if let Some(&new) = remapped.get(blocker) {
+                        *blocker = new;
+                    }
+                }
+                if let Some(new) = task.parent.and_then(|p| remapped.get(&p)) {
+                    task.parent = Some(*new);
+                }
+            }
+            self.save()?;
+        }
+        Ok(changes)
+    }
+
+    fn search_tasks(&self, query: &str, case_sensitive: bool) -> Vec<&Task> {
+        let fold = |text: &str| {
+            if case_sensitive {
+                text.to_string()
+            } else {
+                text.to_lowercase()
+            }
+        };
+        let query = fold(query);
+        self.tasks
+            .iter()
+            .filter(|t| {
+                fold(&t.title).contains(&query)
+                    || t.description
+                        .as_ref()
+                        .is_some_and(|d| fold(d).contains(&query))
             })
             .collect()
     }
 
-    fn complete_task(&mut self, id: usize) -> Result<(), String> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.completed = true;
-            self.save()?;
-            Ok(())
-        } else {
-            Err(format!("Task with ID {} not found", id))
-        }
+    fn fuzzy_search_tasks(&self, query: &str, limit: usize, case_sensitive: bool) -> Vec<&Task> {
+        let matcher = SkimMatcherV2::default();
+        let matcher = if case_sensitive {
+            matcher.respect_case()
+        } else {
+            matcher.ignore_case()
+        };
+        let mut scored: Vec<(i64, &Task)> = self
+            .tasks
+            .iter()
+            .filter_map(|t| matcher.fuzzy_match(&t.title, query).map(|score| (score, t)))
+            .collect();
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        scored.into_iter().take(limit).map(|(_, t)| t).collect()
+    }
+
+    fn show_task(&self, id: usize) -> Result<&Task, String> {
+        self.tasks
+            .iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Task with ID {} not found", id))
+    }
+
+    /// Looks a task up by id, or else by a case-insensitive title substring
+    /// that must match exactly one task (only pending ones if `pending_only`).
+    fn find_task(&self, query: &str, pending_only: bool) -> Result<&Task, String> {
+        if let Ok(id) = query.parse() {
+            return self.show_task(id);
+        }
+        let needle = query.to_lowercase();
+        let matches: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|t| !(pending_only && t.completed))
+            .filter(|t| t.title.to_lowercase().contains(&needle))
+            .collect();
+        match matches.as_slice() {
+            [] if pending_only => Err(format!("No pending task title contains '{}'", query)),
+            [] => Err(format!("No task title contains '{}'", query)),
+            [task] => Ok(task),
+            _ => {
+                let candidates: Vec<String> = matches
+                    .iter()
+                    .map(|t| format!("  {}: {}", t.id, t.title))
+                    .collect();
+                Err(format!(
+                    "'{}' matches {} tasks, use an id instead:\n{}",
+                    query,
+                    matches.len(),
+                    candidates.join("\n")
+                ))
+            }
+        }
+    }
+}
+
+/// Current shape of task files: `{ "version": 3, "next_id": 12, "tasks": [...] }`.
+/// Version 2 files have no `next_id`, and version 1 files are a bare array of
+/// tasks; both are still read.
+const SCHEMA_VERSION: u32 = 3;
+
+#[derive(Serialize)]
+struct TaskFileRef<'a> {
+    version: u32,
+    next_id: usize,
+    tasks: &'a [Task],
+}
+
+#[derive(Deserialize)]
+struct TaskFile {
+    version: u32,
+    #[serde(default)]
+    next_id: usize,
+    tasks: Vec<Task>,
+}
+
+impl TaskFile {
+    #[cfg(not(feature = "sqlite"))]
+    fn empty() -> Self {
+        TaskFile {
+            version: SCHEMA_VERSION,
+            next_id: 1,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+fn serialize_tasks(tasks: &[Task], next_id: usize) -> Result<String, String> {
+    serde_json::to_string_pretty(&TaskFileRef {
+        version: SCHEMA_VERSION,
+        next_id,
+        tasks,
+    })
+    .map_err(|e| format!("Failed to serialize tasks: {}", e))
+}
+
+/// Parses task file contents in any supported format version. `next_id` is
+/// raised above every stored id, since older or hand-edited files may lack it.
+fn parse_tasks(contents: &[u8]) -> Result<TaskFile, String> {
+    let value: serde_json::Value = serde_json::from_slice(contents).map_err(|e| e.to_string())?;
+    let mut file = if value.is_array() {
+        TaskFile {
+            version: 1,
+            next_id: 0,
+            tasks: serde_json::from_value(value).map_err(|e| e.to_string())?,
+        }
+    } else {
+        serde_json::from_value(value).map_err(|e| e.to_string())?
+    };
+    if file.version > SCHEMA_VERSION {
+        return Err(format!(
+            "format version {} is newer than this version of todo supports ({})",
+            file.version, SCHEMA_VERSION
+        ));
+    }
+    let highest = file.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+    file.next_id = file.next_id.max(highest + 1);
+    Ok(file)
+}
+
+fn load_task_file(path: &Path) -> Result<TaskFile, String> {
+    let contents =
+        fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_tasks(&contents).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn read_task_file(path: &Path) -> Result<Vec<Task>, String> {
+    load_task_file(path).map(|file| file.tasks)
+}
+
+/// Drops seconds and below, matching the precision of due dates given by hand.
+fn truncate_to_minute(at: NaiveDateTime) -> NaiveDateTime {
+    at.with_second(0)
+        .and_then(|at| at.with_nanosecond(0))
+        .unwrap_or(at)
+}
+
+/// Parses a duration such as `30m`, `2h`, `1d` or `1w`.
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input.len() - input.chars().last().map(char::len_utf8).unwrap_or(0);
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| {
+        format!(
+            "Invalid duration '{}': expected e.g. 30m, 2h, 1d, 1w",
+            input
+        )
+    })?;
+    let duration = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => {
+            return Err(format!(
+                "Invalid duration unit in '{}': use m, h, d or w",
+                input
+            ))
+        }
+    };
+    duration.ok_or_else(|| format!("Duration '{}' is too large", input))
+}
+
+/// Like `parse_duration`, but for commands where going backwards makes no sense.
+fn parse_non_negative_duration(input: &str) -> Result<Duration, String> {
+    let duration = parse_duration(input)?;
+    if duration < Duration::zero() {
+        return Err(format!("Duration '{}' must not be negative", input.trim()));
+    }
+    Ok(duration)
+}
+
+/// Parses an effort estimate such as `45m` or `2h` into whole minutes.
+fn parse_estimate(input: &str) -> Result<u32, String> {
+    let minutes = parse_duration(input)?.num_minutes();
+    if minutes <= 0 {
+        return Err(format!("Estimate '{}' must be at least one minute", input));
+    }
+    u32::try_from(minutes).map_err(|_| format!("Estimate '{}' is too large", input))
+}
+
+/// Formats minutes as e.g. "4h 15m", "2h" or "45m".
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// The candidate closest to `input`, if it is a plausible typo (at most two
+/// edits away and shorter than the input itself).
+fn closest_match<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|c| (edit_distance(input, c), *c))
+        .filter(|&(distance, _)| distance <= 2 && distance < input.chars().count())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, c)| c)
+}
+
+/// Warns when `category` looks like a typo of a built-in category and no task
+/// uses it yet. The category is still accepted as a custom one.
+fn warn_category_typo(opts: &GlobalOpts, manager: &TodoManager, category: &str) {
+    if manager.tasks.iter().any(|t| t.category.matches(category)) {
+        return;
+    }
+    if let Some(hint) = Category::suggestion(category) {
+        opts.warn(&format!(
+            "Warning: '{}' isn't a built-in category, did you mean '{}'?",
+            category.trim(),
+            hint
+        ));
+    }
+}
+
+fn validate_title(title: &str) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Task title cannot be empty.".to_string());
+    }
+    Ok(())
+}
+
+fn parse_due_date(date_str: &str) -> Result<NaiveDateTime, String> {
+    let format = if STRICT_DATES.load(atomic::Ordering::Relaxed) {
+        DEFAULT_DATE_FORMAT
+    } else {
+        config().date_format()
+    };
+    NaiveDateTime::parse_from_str(date_str, format)
+        .map_err(|e| format!("Invalid date format: {}", e))
+}
+
+/// Parses a batch line of the form `title | priority | category | tags`, where
+/// everything after the title is optional.
+fn parse_batch_line(id: usize, line: &str) -> Result<Task, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() > 4 {
+        return Err("too many '|' separated fields".to_string());
     }
+    let field = |index: usize| fields.get(index).copied().filter(|f| !f.is_empty());
 
-    fn remove_task(&mut self, id: usize) -> Result<(), String> {
-        if let Some(index) = self.tasks.iter().position(|t| t.id == id) {
-            self.tasks.remove(index);
-            self.save()?;
-            Ok(())
-        } else {
-            Err(format!("Task with ID {} not found", id))
-        }
+    let title = field(0).ok_or("missing title")?;
+    Task::new(
+        id,
+        title.to_string(),
+        None,
+        None,
+        field(1).unwrap_or(config().default_priority()),
+        field(2).unwrap_or(config().default_category()),
+        field(3).map(str::to_string),
+    )
+}
+
+/// Lists the fields that differ between two versions of a task, one
+/// "field: 'old' -> 'new'" line each.
+fn describe_changes(before: &Task, after: &Task) -> Vec<String> {
+    let ids = |ids: &[usize]| {
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let due = |task: &Task| {
+        task.due_date
+            .map(|d| d.format(config().date_format()).to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let fields = [
+        ("title", before.title.clone(), after.title.clone()),
+        (
+            "description",
+            before.description.clone().unwrap_or_default(),
+            after.description.clone().unwrap_or_default(),
+        ),
+        ("due", due(before), due(after)),
+        (
+            "priority",
+            format!("{:?}", before.priority),
+            format!("{:?}", after.priority),
+        ),
+        (
+            "category",
+            before.category.name().to_string(),
+            after.category.name().to_string(),
+        ),
+        ("tags", before.tags.join(", "), after.tags.join(", ")),
+        (
+            "blocked_by",
+            ids(&before.blocked_by),
+            ids(&after.blocked_by),
+        ),
+        (
+            "parent",
+            before.parent.map(|p| p.to_string()).unwrap_or_default(),
+            after.parent.map(|p| p.to_string()).unwrap_or_default(),
+        ),
+        (
+            "estimate",
+            before.estimate.map(format_minutes).unwrap_or_default(),
+            after.estimate.map(format_minutes).unwrap_or_default(),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| format!("{}: '{}' -> '{}'", field, old, new))
+        .collect()
+}
+
+/// Joins a comma-separated `--tags` list and repeated `--tag` values into one
+/// list for `parse_tags`, or `None` when neither was given.
+fn combine_tags(tags: Option<String>, tag: Vec<String>) -> Option<String> {
+    if tag.is_empty() {
+        return tags;
     }
+    Some(tags.into_iter().chain(tag).collect::<Vec<_>>().join(","))
+}
 
-    fn edit_task(&mut self, id: usize, updates: TaskUpdates) -> Result<(), String> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            if let Some(title) = updates.title {
-                task.title = title;
-            }
-            if let Some(description) = updates.description {
-                task.description = Some(description);
-            }
-            if let Some(due_date) = updates.due {
-                task.due_date = Some(
-                    NaiveDateTime::parse_from_str(&due_date, "%Y-%m-%d %H:%M")
-                        .map_err(|e| format!("Invalid date format: {}", e))?,
-                );
-            }
-            if let Some(priority) = updates.priority {
-                task.priority = match priority.to_lowercase().as_str() {
-                    "low" => Priority::Low,
-                    "medium" => Priority::Medium,
-                    "high" => Priority::High,
-                    "critical" => Priority::Critical,
-                    _ => return Err("Invalid priority level".to_string()),
-                };
-            }
-            if let Some(category) = updates.category {
-                task.category = match category.to_lowercase().as_str() {
-                    "personal" => Category::Personal,
-                    "work" => Category::Work,
-                    "shopping" => Category::Shopping,
-                    "health" => Category::Health,
-                    other => Category::Other(other.to_string()),
-                };
-            }
-            if let Some(tags) = updates.tags {
-                task.tags = tags.split(',').map(|s| s.trim().to_string()).collect();
-            }
-            self.save()?;
-            Ok(())
-        } else {
-            Err(format!("Task with ID {} not found", id))
-        }
+/// Validates one `--tag` value. Tag values are joined and split again on
+/// commas, so a comma would silently turn one tag into two.
+fn parse_single_tag(tag: &str) -> Result<String, String> {
+    if tag.contains(',') {
+        return Err(format!(
+            "tag '{}' contains a comma; pass each tag separately or use --tags",
+            tag
+        ));
     }
+    Ok(tag.to_string())
+}
 
-    fn show_task(&self, id: usize) -> Result<&Task, String> {
-        self.tasks
-            .iter()
-            .find(|t| t.id == id)
-            .ok_or_else(|| format!("Task with ID {} not found", id))
+fn parse_tags(tags: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for tag in tags.split(',') {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !parsed.contains(&tag) {
+            parsed.push(tag);
+        }
     }
+    parsed
 }
 
+#[derive(Default)]
 struct ListFilters {
-    category: Option<String>,
-    priority: Option<String>,
+    category: Vec<String>,
+    priority: Vec<String>,
+    tag: Option<String>,
     completed: bool,
     pending: bool,
+    due_before: Option<NaiveDateTime>,
+    due_after: Option<NaiveDateTime>,
+    created_before: Option<NaiveDateTime>,
+    created_after: Option<NaiveDateTime>,
+    completed_before: Option<NaiveDateTime>,
+    completed_after: Option<NaiveDateTime>,
+    min_priority: Option<Priority>,
+    /// `Some(false)` keeps only unscheduled tasks, `Some(true)` only scheduled ones.
+    has_due: Option<bool>,
+}
+
+impl From<FilterArgs> for ListFilters {
+    fn from(filter: FilterArgs) -> Self {
+        ListFilters {
+            category: filter.category,
+            priority: filter.priority,
+            tag: filter.tag,
+            ..Default::default()
+        }
+    }
+}
+
+impl ListFilters {
+    fn matches(&self, task: &Task) -> bool {
+        let category_match =
+            self.category.is_empty() || self.category.iter().any(|c| task.category.matches(c));
+
+        let priority_match = self.priority.is_empty()
+            || self
+                .priority
+                .iter()
+                .any(|p| format!("{:?}", task.priority).eq_ignore_ascii_case(p));
+        let min_priority_match = self.min_priority.is_none_or(|min| task.priority >= min);
+        let has_due_match = self
+            .has_due
+            .is_none_or(|has_due| task.due_date.is_some() == has_due);
+
+        let tag_match = self
+            .tag
+            .as_ref()
+            .map(|tag| task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            .unwrap_or(true);
+
+        let completion_match = if self.completed {
+            task.completed
+        } else if self.pending {
+            !task.completed
+        } else {
+            true
+        };
+
+        let due_match = if self.due_before.is_some() || self.due_after.is_some() {
+            task.due_date
+                .map(|d| {
+                    self.due_before.map(|b| d <= b).unwrap_or(true)
+                        && self.due_after.map(|a| d >= a).unwrap_or(true)
+                })
+                .unwrap_or(false)
+        } else {
+            true
+        };
+
+        let created = task.created_at.naive_local();
+        let created_match = self.created_before.map(|b| created <= b).unwrap_or(true)
+            && self.created_after.map(|a| created >= a).unwrap_or(true);
+
+        let completed_at_match =
+            if self.completed_before.is_some() || self.completed_after.is_some() {
+                task.completed_at
+                    .map(|at| {
+                        let at = at.naive_local();
+                        self.completed_before.map(|b| at <= b).unwrap_or(true)
+                            && self.completed_after.map(|a| at >= a).unwrap_or(true)
+                    })
+                    .unwrap_or(false)
+            } else {
+                true
+            };
+
+        category_match
+            && priority_match
+            && min_priority_match
+            && has_due_match
+            && tag_match
+            && completion_match
+            && due_match
+            && created_match
+            && completed_at_match
+    }
 }
 
+#[derive(Default)]
 struct TaskUpdates {
     title: Option<String>,
     description: Option<String>,
@@ -341,34 +2363,545 @@ struct TaskUpdates {
     priority: Option<String>,
     category: Option<String>,
     tags: Option<String>,
+    blocked_by: Option<Vec<usize>>,
+    /// `Some(None)` detaches the task from its parent.
+    parent: Option<Option<usize>>,
+    estimate: Option<String>,
+}
+
+fn compare_due_dates(a: &Task, b: &Task) -> Ordering {
+    match (a.due_date, b.due_date) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts by `key` (optionally reversed), breaking ties by earliest due date and
+/// then by id so the order is the same on every run.
+fn sort_tasks(tasks: &mut [&Task], key: SortKey, reverse: bool) {
+    tasks.sort_by(|a, b| {
+        let primary = match key {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Priority => b.priority.cmp(&a.priority),
+            SortKey::Due => compare_due_dates(a, b),
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+            SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortKey::Tags => b.tags.len().cmp(&a.tags.len()),
+        };
+        let primary = if reverse { primary.reverse() } else { primary };
+        primary
+            .then_with(|| compare_due_dates(a, b))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+fn display_tasks(tasks: Vec<&Task>, columns: &[Column], blocked: &HashSet<usize>, summary: bool) {
+    let summary = task_summary(&tasks).filter(|_| summary);
+    let _ = write_tasks(&mut io::stdout(), tasks, columns, blocked, false);
+    if let Some(summary) = summary {
+        println!("{}", summary);
+    }
+}
+
+/// "7 tasks (3 pending, 4 completed, 1 overdue)", or `None` when there are
+/// no tasks to sum up.
+fn task_summary(tasks: &[&Task]) -> Option<String> {
+    if tasks.is_empty() {
+        return None;
+    }
+    let completed = tasks.iter().filter(|t| t.completed).count();
+    let overdue = tasks.iter().filter(|t| t.is_overdue()).count();
+    Some(format!(
+        "{} task{} ({} pending, {} completed, {} overdue)",
+        tasks.len(),
+        if tasks.len() == 1 { "" } else { "s" },
+        tasks.len() - completed,
+        completed,
+        overdue
+    ))
+}
+
+fn write_tasks(
+    out: &mut dyn Write,
+    mut tasks: Vec<&Task>,
+    columns: &[Column],
+    blocked: &HashSet<usize>,
+    relative: bool,
+) -> io::Result<()> {
+    if tasks.is_empty() {
+        writeln!(out, "No tasks found.")?;
+        return Ok(());
+    }
+
+    // Stable, so pinned and unpinned tasks each keep their sort order.
+    tasks.sort_by_key(|t| !t.pinned);
+
+    let mut table = new_table();
+    table.add_row(Row::new(
+        columns.iter().map(|c| Cell::new(c.header())).collect(),
+    ));
+
+    for task in tasks {
+        table.add_row(task.to_row(columns, blocked.contains(&task.id), relative));
+    }
+
+    table.print(out)?;
+    Ok(())
+}
+
+/// Describes a due date relative to now: "in 2 days", "3 hours ago" for
+/// completed tasks, or "overdue by 1 day" for pending ones.
+fn relative_due(due: NaiveDateTime, completed: bool) -> String {
+    let until_due = due - Local::now().naive_local();
+    if until_due >= Duration::zero() {
+        format!("in {}", humanize_duration(until_due))
+    } else if completed {
+        format!("{} ago", humanize_duration(until_due))
+    } else {
+        format!("overdue by {}", humanize_duration(until_due))
+    }
+}
+
+/// Renders the magnitude of a duration in its largest whole unit, e.g. "3 days"
+/// or "1 hour". The sign is ignored; callers add "ago"/"in" as appropriate.
+fn humanize_duration(duration: Duration) -> String {
+    let duration = duration.abs();
+    let (amount, unit) = if duration.num_days() >= 1 {
+        (duration.num_days(), "day")
+    } else if duration.num_hours() >= 1 {
+        (duration.num_hours(), "hour")
+    } else if duration.num_minutes() >= 1 {
+        (duration.num_minutes(), "minute")
+    } else {
+        return "less than a minute".to_string();
+    };
+
+    if amount == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", amount, unit)
+    }
+}
+
+/// Writes `tasks` as an indented tree. Tasks whose parent isn't among `tasks`
+/// are roots; subtasks are looked up in `all`, so completed ones still show.
+fn write_task_tree(
+    out: &mut dyn Write,
+    tasks: Vec<&Task>,
+    all: &[Task],
+    blocked: &HashSet<usize>,
+) -> io::Result<()> {
+    if tasks.is_empty() {
+        writeln!(out, "No tasks found.")?;
+        return Ok(());
+    }
+
+    let listed: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+    for root in tasks
+        .iter()
+        .filter(|t| !t.parent.is_some_and(|p| listed.contains(&p)))
+    {
+        writeln!(out, "{}", tree_label(root, blocked))?;
+        write_subtasks(out, root.id, all, blocked, "")?;
+    }
+    Ok(())
+}
+
+fn write_subtasks(
+    out: &mut dyn Write,
+    parent: usize,
+    all: &[Task],
+    blocked: &HashSet<usize>,
+    prefix: &str,
+) -> io::Result<()> {
+    let children: Vec<&Task> = all.iter().filter(|t| t.parent == Some(parent)).collect();
+    for (index, child) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        let connector = if last { "└─" } else { "├─" };
+        writeln!(
+            out,
+            "{}{} {}",
+            prefix,
+            connector,
+            tree_label(child, blocked)
+        )?;
+        let indent = if last { "   " } else { "│  " };
+        write_subtasks(
+            out,
+            child.id,
+            all,
+            blocked,
+            &format!("{}{}", prefix, indent),
+        )?;
+    }
+    Ok(())
+}
+
+fn tree_label(task: &Task, blocked: &HashSet<usize>) -> String {
+    let status = if blocked.contains(&task.id) {
+        blocked_symbol()
+    } else {
+        status_symbol(task.completed)
+    };
+    format!("{} {}: {}", status, task.id, task.title)
+}
+
+/// Sections of `list --by-date`, most urgent first. Completed tasks get their
+/// own section at the end, whatever their due date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DueBucket {
+    Overdue,
+    Today,
+    ThisWeek,
+    Later,
+    NoDate,
+    Completed,
+}
+
+impl DueBucket {
+    fn of(task: &Task, now: NaiveDateTime) -> Self {
+        if task.completed {
+            return DueBucket::Completed;
+        }
+        let Some(due) = task.due_date else {
+            return DueBucket::NoDate;
+        };
+        let today = now.date();
+        let end_of_week = today + Duration::days(6 - today.weekday().num_days_from_monday() as i64);
+        if due < now {
+            DueBucket::Overdue
+        } else if due.date() == today {
+            DueBucket::Today
+        } else if due.date() <= end_of_week {
+            DueBucket::ThisWeek
+        } else {
+            DueBucket::Later
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DueBucket::Overdue => "Overdue",
+            DueBucket::Today => "Today",
+            DueBucket::ThisWeek => "This Week",
+            DueBucket::Later => "Later",
+            DueBucket::NoDate => "No date",
+            DueBucket::Completed => "Completed",
+        }
+    }
+}
+
+fn write_grouped_tasks(
+    out: &mut dyn Write,
+    mut tasks: Vec<&Task>,
+    columns: &[Column],
+    group_by: &str,
+    blocked: &HashSet<usize>,
+    relative: bool,
+) -> io::Result<()> {
+    if tasks.is_empty() {
+        writeln!(out, "No tasks found.")?;
+        return Ok(());
+    }
+
+    let now = Local::now().naive_local();
+    match group_by {
+        "priority" => tasks.sort_by_key(|t| Reverse(t.priority)),
+        "date" => tasks.sort_by_key(|t| DueBucket::of(t, now)),
+        _ => {}
+    }
+
+    let mut groups: Vec<(String, Vec<&Task>)> = Vec::new();
+    for task in tasks {
+        let name = match group_by {
+            "priority" => format!("{:?}", task.priority),
+            "date" => DueBucket::of(task, now).name().to_string(),
+            _ => task.category.name().to_string(),
+        };
+        match groups.iter_mut().find(|(group, _)| *group == name) {
+            Some((_, group_tasks)) => group_tasks.push(task),
+            None => groups.push((name, vec![task])),
+        }
+    }
+
+    for (name, group_tasks) in groups {
+        writeln!(out, "== {} ==", name)?;
+        write_tasks(out, group_tasks, columns, blocked, relative)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn display_legend() {
+    println!("{}", "Priorities:".bold());
+    for (priority, description) in [
+        (Priority::Low, "whenever there's time"),
+        (Priority::Medium, "the default for new tasks"),
+        (Priority::High, "should be done soon"),
+        (Priority::Critical, "drop everything else"),
+    ] {
+        println!("  {:<8} {}", priority.colored(), description);
+    }
+
+    println!("\n{}", "Status:".bold());
+    println!("  {} completed", status_symbol(true));
+    println!("  {} pending", status_symbol(false));
+    println!("  {} waiting on a pending task", blocked_symbol());
+    println!("  {} pending and due later today", due_today_symbol());
+
+    println!("\n{}", "Due dates:".bold());
+    println!(
+        "  {} a pending task past its due date",
+        Local::now()
+            .naive_local()
+            .format(config().date_format())
+            .to_string()
+            .red()
+    );
+}
+
+#[derive(Serialize)]
+struct Stats {
+    total: usize,
+    completed: usize,
+    pending: usize,
+    completion_percent: f64,
+    by_priority: BTreeMap<String, usize>,
+    by_category: BTreeMap<String, usize>,
+    overdue: usize,
+}
+
+impl Stats {
+    fn from_tasks(tasks: &[Task]) -> Self {
+        let completed = tasks.iter().filter(|t| t.completed).count();
+        let completion_percent = if tasks.is_empty() {
+            0.0
+        } else {
+            (completed as f64 * 1000.0 / tasks.len() as f64).round() / 10.0
+        };
+
+        let mut by_priority: BTreeMap<String, usize> = [
+            Priority::Low,
+            Priority::Medium,
+            Priority::High,
+            Priority::Critical,
+        ]
+        .iter()
+        .map(|p| (format!("{:?}", p), 0))
+        .collect();
+        let mut by_category = BTreeMap::new();
+        for task in tasks {
+            *by_priority
+                .entry(format!("{:?}", task.priority))
+                .or_default() += 1;
+            *by_category
+                .entry(task.category.name().to_string())
+                .or_default() += 1;
+        }
+
+        Stats {
+            total: tasks.len(),
+            completed,
+            pending: tasks.len() - completed,
+            completion_percent,
+            by_priority,
+            by_category,
+            overdue: tasks.iter().filter(|t| t.is_overdue()).count(),
+        }
+    }
+
+    const FIELDS: [&'static str; 5] = [
+        "total",
+        "completed",
+        "pending",
+        "completion_percent",
+        "overdue",
+    ];
+
+    /// A single metric as a bare value, for `stats --field`.
+    fn field(&self, name: &str) -> Result<String, String> {
+        Ok(match name {
+            "total" => self.total.to_string(),
+            "completed" => self.completed.to_string(),
+            "pending" => self.pending.to_string(),
+            "completion_percent" => self.completion_percent.to_string(),
+            "overdue" => self.overdue.to_string(),
+            _ => {
+                return Err(format!(
+                    "Unknown field '{}', expected one of: {}",
+                    name,
+                    Self::FIELDS.join(", ")
+                ))
+            }
+        })
+    }
 }
 
-fn display_tasks(tasks: Vec<&Task>) {
+fn display_stats(stats: &Stats) {
+    let mut table = new_table();
+    for (label, value) in stats_rows(stats) {
+        table.add_row(Row::new(vec![Cell::new(&label), Cell::new(&value)]));
+    }
+    print_key_values(&table);
+}
+
+/// The labelled values shown by `stats`, in display order.
+fn stats_rows(stats: &Stats) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    let mut add = |label: &str, value: String| rows.push((label.to_string(), value));
+    add("Total", stats.total.to_string());
+    add(
+        "Completed",
+        format!("{} ({}%)", stats.completed, stats.completion_percent),
+    );
+    add("Pending", stats.pending.to_string());
+    add("Overdue", stats.overdue.to_string());
+    for priority in [
+        Priority::Critical,
+        Priority::High,
+        Priority::Medium,
+        Priority::Low,
+    ] {
+        let name = format!("{:?}", priority);
+        let count = stats.by_priority.get(&name).copied().unwrap_or(0);
+        add(&format!("Priority: {}", name), count.to_string());
+    }
+    for (category, count) in &stats.by_category {
+        add(&format!("Category: {}", category), count.to_string());
+    }
+    rows
+}
+
+/// What `render` can draw.
+enum Rendered<'a> {
+    Tasks(&'a [&'a Task]),
+    Task(&'a Task),
+    Stats(&'a Stats),
+}
+
+/// Renders `data` as text in `format`. Returns `None` for `table`, which each
+/// command draws itself since its table has command-specific options.
+fn render(format: OutputFormat, data: Rendered) -> Result<Option<String>, String> {
+    let json = |value: Result<String, serde_json::Error>| {
+        value
+            .map(|v| v + "\n")
+            .map_err(|e| format!("Failed to serialize: {}", e))
+    };
+    let header = ["metric", "value"];
+    Ok(Some(match (format, data) {
+        (OutputFormat::Table, _) => return Ok(None),
+        (OutputFormat::Csv, Rendered::Tasks(tasks)) => export::to_csv(tasks),
+        (OutputFormat::Csv, Rendered::Task(task)) => export::to_csv(&[task]),
+        (OutputFormat::Csv, Rendered::Stats(stats)) => {
+            export::key_values_to_csv(header, &stats_rows(stats))
+        }
+        (OutputFormat::Markdown, Rendered::Tasks(tasks)) => export::to_markdown(tasks),
+        (OutputFormat::Markdown, Rendered::Task(task)) => export::to_markdown(&[task]),
+        (OutputFormat::Markdown, Rendered::Stats(stats)) => {
+            export::key_values_to_markdown(header, &stats_rows(stats))
+        }
+        (OutputFormat::Json, Rendered::Tasks(tasks)) => json(json_string(tasks))?,
+        (OutputFormat::Json, Rendered::Task(task)) => json(json_string(task))?,
+        (OutputFormat::Json, Rendered::Stats(stats)) => json(json_string(stats))?,
+        (OutputFormat::Jsonl, Rendered::Tasks(tasks)) => export::to_jsonl(tasks)?,
+        (OutputFormat::Jsonl, Rendered::Task(task)) => export::to_jsonl(&[task])?,
+        (OutputFormat::Ics | OutputFormat::Html, _) | (OutputFormat::Jsonl, Rendered::Stats(_)) => {
+            return Err(format!(
+                "--format {} is only supported by export",
+                format.name()
+            ))
+        }
+    }))
+}
+
+fn display_progress(tasks: &[Task]) {
     if tasks.is_empty() {
         println!("No tasks found.");
         return;
     }
 
-    let mut table = Table::new();
-    table.add_row(row![
-        "ID",
-        "Status",
-        "Title",
-        "Due Date",
-        "Priority",
-        "Category",
-        "Tags"
-    ]);
-
+    let mut categories: Vec<(&str, usize, usize)> = Vec::new();
     for task in tasks {
-        table.add_row(task.to_row());
+        let name = task.category.name();
+        let index = match categories.iter().position(|(c, _, _)| *c == name) {
+            Some(index) => index,
+            None => {
+                categories.push((name, 0, 0));
+                categories.len() - 1
+            }
+        };
+        categories[index].2 += 1;
+        if task.completed {
+            categories[index].1 += 1;
+        }
+    }
+    categories.sort_by_key(|&(_, _, total)| Reverse(total));
+
+    const BAR_WIDTH: usize = 10;
+    let name_width = categories
+        .iter()
+        .map(|(c, _, _)| c.len())
+        .max()
+        .unwrap_or(0);
+    for (name, done, total) in categories {
+        let filled = done * BAR_WIDTH / total;
+        println!(
+            "{:<width$} [{}{}] {:>3}% ({}/{})",
+            name,
+            "█".repeat(filled).green(),
+            "-".repeat(BAR_WIDTH - filled).red(),
+            done * 100 / total,
+            done,
+            total,
+            width = name_width
+        );
+    }
+}
+
+fn display_completion_report(tasks: &[&Task], since: NaiveDateTime) {
+    let mut days: Vec<(NaiveDate, usize)> = Vec::new();
+    let mut undated = 0;
+    for task in tasks.iter().filter(|t| t.completed) {
+        match task.completed_at.map(|d| d.naive_local()) {
+            Some(completed_at) if completed_at >= since => {
+                let day = completed_at.date();
+                match days.iter_mut().find(|(d, _)| *d == day) {
+                    Some((_, count)) => *count += 1,
+                    None => days.push((day, 1)),
+                }
+            }
+            Some(_) => {}
+            None => undated += 1,
+        }
     }
+    days.sort();
 
+    let total: usize = days.iter().map(|(_, count)| count).sum();
+    let mut table = new_table();
+    table.add_row(Row::new(vec![Cell::new("Day"), Cell::new("Completed")]));
+    for (day, count) in days {
+        table.add_row(Row::new(vec![
+            Cell::new(&day.format("%Y-%m-%d").to_string()),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    if undated > 0 {
+        table.add_row(Row::new(vec![
+            Cell::new("undated"),
+            Cell::new(&undated.to_string()),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("Total"),
+        Cell::new(&(total + undated).to_string()),
+    ]));
     table.printstd();
 }
 
 fn display_task_details(task: &Task) {
-    let mut table = Table::new();
+    let mut table = new_table();
     table.add_row(Row::new(vec![
         Cell::new("ID"),
         Cell::new(&task.id.to_string()),
@@ -391,89 +2924,793 @@ fn display_task_details(task: &Task) {
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Created"),
-        Cell::new(&task.created_at.format("%Y-%m-%d %H:%M").to_string()),
+        Cell::new(&format!(
+            "{} ({} ago)",
+            task.created_at.format(config().date_format()),
+            humanize_duration(Local::now() - task.created_at)
+        )),
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Due Date"),
         Cell::new(
             &task
                 .due_date
-                .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                .map(|d| {
+                    let due = format!(
+                        "{} ({})",
+                        d.format(config().date_format()),
+                        relative_due(d, task.completed)
+                    );
+                    if task.is_overdue() {
+                        due.red().to_string()
+                    } else {
+                        due
+                    }
+                })
                 .unwrap_or_else(|| "-".to_string()),
         ),
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Priority"),
-        Cell::new(&format!("{:?}", task.priority)),
+        Cell::new(&task.priority.colored().to_string()),
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Category"),
-        Cell::new(&format!("{:?}", task.category)),
+        Cell::new(&task.category.colored().to_string()),
     ]));
     table.add_row(Row::new(vec![
         Cell::new("Tags"),
         Cell::new(&task.tags.join(", ")),
     ]));
+    if let Some(estimate) = task.estimate {
+        table.add_row(Row::new(vec![
+            Cell::new("Estimate"),
+            Cell::new(&format_minutes(estimate)),
+        ]));
+    }
+    if task.time_spent_minutes > 0 {
+        table.add_row(Row::new(vec![
+            Cell::new("Time Spent"),
+            Cell::new(&format_minutes(task.time_spent_minutes)),
+        ]));
+    }
+    if let Some(parent) = task.parent {
+        table.add_row(Row::new(vec![
+            Cell::new("Parent"),
+            Cell::new(&parent.to_string()),
+        ]));
+    }
+    if !task.blocked_by.is_empty() {
+        table.add_row(Row::new(vec![
+            Cell::new("Blocked By"),
+            Cell::new(
+                &task
+                    .blocked_by
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ]));
+    }
+    if !task.notes.is_empty() {
+        table.add_row(Row::new(vec![
+            Cell::new("Notes"),
+            Cell::new(&task.notes.join("\n")),
+        ]));
+    }
 
-    table.printstd();
+    print_key_values(&table);
+}
+
+/// Opens `$EDITOR` (or `$VISUAL`, falling back to vi/notepad) on a temporary
+/// file containing `initial` and returns the saved contents.
+/// Creates a new file with an unguessable name in the temp directory, readable
+/// only by the current user. `create_new` refuses existing files and symlinks,
+/// so another user on a shared temp directory can't redirect the write.
+fn create_temp_file() -> io::Result<(PathBuf, fs::File)> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    for attempt in 0..100 {
+        let name = RandomState::new().hash_one((process::id(), Instant::now(), attempt));
+        let path = env::temp_dir().join(format!("todo-description-{:016x}.txt", name));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "no unused temp file name found",
+    ))
+}
+
+fn edit_in_editor(initial: &str) -> Result<String, String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+    let mut words = shlex::split(&editor)
+        .filter(|w| !w.is_empty())
+        .ok_or_else(|| format!("Invalid editor command: {}", editor))?;
+    let program = words.remove(0);
+
+    let initial = if initial.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", initial)
+    };
+    let (path, mut file) =
+        create_temp_file().map_err(|e| format!("Failed to create temp file: {}", e))?;
+    let written = file.write_all(initial.as_bytes());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Failed to write temp file: {}", e));
+    }
+    let status = process::Command::new(&program)
+        .args(&words)
+        .arg(&path)
+        .status();
+    let contents = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(format!("Editor exited with {}", status)),
+        Err(e) => return Err(format!("Failed to launch editor '{}': {}", program, e)),
+    }
+    let contents = contents.map_err(|e| format!("Failed to read temp file: {}", e))?;
+    Ok(contents.trim_end().to_string())
+}
+
+/// Starts `$PAGER` (or `less -R`) reading from a pipe. Returns `None` when
+/// stdout isn't a terminal or the pager can't be started, so callers can fall
+/// back to printing directly.
+fn spawn_pager() -> Option<process::Child> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut words = shlex::split(&pager).filter(|w| !w.is_empty())?;
+    let program = words.remove(0);
+    process::Command::new(program)
+        .args(words)
+        .stdin(process::Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+/// Deleting more tasks than this at once requires `--force`, even with `--yes`.
+const MASS_DELETE_LIMIT: usize = 10;
+
+fn check_mass_delete(count: usize, force: bool) -> Result<(), String> {
+    if count > MASS_DELETE_LIMIT && !force {
+        return Err(format!(
+            "Refusing to delete {} tasks without --force.",
+            count
+        ));
+    }
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Counts down `minutes` on a single line. Interrupting with Ctrl-C simply
+/// ends the process, since nothing is saved until the session is over.
+fn run_focus_timer(minutes: u64) -> io::Result<()> {
+    let end = Instant::now() + std::time::Duration::from_secs(minutes * 60);
+    let mut stdout = io::stdout();
+    loop {
+        let remaining = end.saturating_duration_since(Instant::now()).as_secs();
+        write!(
+            stdout,
+            "\r{} {:02}:{:02} remaining ",
+            "Focus:".bold(),
+            remaining / 60,
+            remaining % 60
+        )?;
+        stdout.flush()?;
+        if remaining == 0 {
+            writeln!(stdout)?;
+            return Ok(());
+        }
+        thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
+
+/// Prints `label` and reads one trimmed line from stdin. End of input aborts.
+fn prompt(label: &str) -> Result<String, String> {
+    print!("{}: ", label);
+    io::stdout()
+        .flush()
+        .map_err(|e| format!("Failed to write prompt: {}", e))?;
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) => Err("Aborted".to_string()),
+        Ok(_) => Ok(answer.trim().to_string()),
+        Err(e) => Err(format!("Failed to read input: {}", e)),
+    }
+}
+
+/// Asks until `parse` accepts the answer, printing each error. Empty answers
+/// are passed to `parse` too, so it decides what the default is.
+fn prompt_until<T>(label: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<T, String> {
+    loop {
+        match parse(&prompt(label)?) {
+            Ok(value) => return Ok(value),
+            Err(e) => eprintln!("{}", e.red()),
+        }
+    }
+}
+
+/// Builds a task from prompts for `add-interactive`.
+fn prompt_task(id: usize) -> Result<Task, String> {
+    let title = prompt_until("Title", |title| {
+        validate_title(title).map(|_| title.to_string())
+    })?;
+    let description = Some(prompt("Description (optional)")?).filter(|d| !d.is_empty());
+    let due = prompt_until(
+        &format!("Due date ({}, optional)", config().date_format()),
+        |due| {
+            if due.is_empty() {
+                return Ok(None);
+            }
+            parse_due_date(due).map(|_| Some(due.to_string()))
+        },
+    )?;
+
+    let priorities = [
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Critical,
+    ];
+    for (number, priority) in priorities.iter().enumerate() {
+        println!("  {}) {}", number + 1, priority.colored());
+    }
+    let default_priority = config().default_priority();
+    let priority = prompt_until(&format!("Priority [{}]", default_priority), |answer| {
+        if answer.is_empty() {
+            return Priority::parse(default_priority);
+        }
+        match answer.parse::<usize>() {
+            Ok(number) => priorities
+                .get(number.wrapping_sub(1))
+                .copied()
+                .ok_or_else(|| format!("Pick a number from 1 to {}", priorities.len())),
+            Err(_) => Priority::parse(answer),
+        }
+    })?;
+
+    let default_category = config().default_category();
+    let category = prompt_until(&format!("Category [{}]", default_category), |answer| {
+        let name = if answer.is_empty() {
+            default_category
+        } else {
+            answer
+        };
+        Category::parse(name).map(|_| name.to_string())
+    })?;
+    let tags = Some(prompt("Tags (comma-separated, optional)")?).filter(|t| !t.is_empty());
+
+    Task::new(
+        id,
+        title,
+        description,
+        due,
+        &format!("{:?}", priority),
+        &category,
+        tags,
+    )
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match Config::load() {
+        Ok(loaded) => {
+            let _ = CONFIG.set(loaded);
+            apply_output_mode(cli.global.plain);
+            STRICT_DATES.store(cli.global.strict_date, atomic::Ordering::Relaxed);
+            COMPACT_JSON.store(cli.global.compact, atomic::Ordering::Relaxed);
+        }
+        Err(e) => {
+            eprintln!("Error loading config: {}", e);
+            process::exit(1);
+        }
+    }
+    let mut manager = match TodoManager::new(cli.global.dry_run, cli.global.verbose) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("Error loading tasks: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(cli.command, &mut manager, &cli.global) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
-    let mut manager = TodoManager::new();
-
-    match cli.command {
+fn run(command: Commands, manager: &mut TodoManager, opts: &GlobalOpts) -> Result<(), String> {
+    match command {
         Commands::Add {
             title,
             description,
             due,
+            due_in,
             priority,
             category,
             tags,
+            tag,
+            edit,
+            blocked_by,
+            parent,
+            estimate,
+            yes,
         } => {
-            let id = manager.tasks.len() + 1;
-            match Task::new(id, title, description, due, &priority, &category, tags) {
-                Ok(task) => {
-                    if let Err(e) = manager.add_task(task) {
-                        eprintln!("Error adding task: {}", e);
+            if config().warn_duplicates() {
+                if let Some(existing) = manager
+                    .tasks
+                    .iter()
+                    .find(|t| !t.completed && t.title.eq_ignore_ascii_case(title.trim()))
+                {
+                    let warning = format!(
+                        "Warning: task {} already has this title: {}",
+                        existing.id, existing.title
+                    );
+                    if opts.dry_run || yes {
+                        opts.warn(&warning);
+                    } else if !io::stdin().is_terminal() {
+                        return Err(format!(
+                            "Error adding task: task {} already has this title; pass --yes to add it anyway",
+                            existing.id
+                        ));
                     } else {
-                        println!("Task added successfully!");
+                        // Shown even under --quiet, since it explains the prompt.
+                        eprintln!("{}", warning.yellow());
+                        if !confirm("Add it anyway?") {
+                            return Err("Error adding task: aborted".to_string());
+                        }
                     }
                 }
-                Err(e) => eprintln!("Error creating task: {}", e),
             }
+            let tags = combine_tags(tags, tag);
+            let description = if edit {
+                let text = edit_in_editor("").map_err(|e| format!("Error creating task: {}", e))?;
+                Some(text).filter(|t| !t.is_empty())
+            } else {
+                description
+            };
+            let id = manager.next_id();
+            let priority = priority.as_deref().unwrap_or(config().default_priority());
+            let category = category.as_deref().unwrap_or(config().default_category());
+            let mut task = Task::new(id, title, description, due, priority, category, tags)
+                .map_err(|e| format!("Error creating task: {}", e))?;
+            warn_category_typo(opts, manager, category);
+            manager
+                .validate_blockers(id, &blocked_by)
+                .map_err(|e| format!("Error creating task: {}", e))?;
+            task.blocked_by = blocked_by;
+            if let Some(estimate) = estimate {
+                task.estimate = Some(
+                    parse_estimate(&estimate).map_err(|e| format!("Error creating task: {}", e))?,
+                );
+            }
+            if let Some(due_in) = due_in {
+                let offset =
+                    parse_duration(&due_in).map_err(|e| format!("Error creating task: {}", e))?;
+                let due_date = task
+                    .created_at
+                    .checked_add_signed(offset)
+                    .ok_or_else(|| "Error creating task: due date is out of range".to_string())?;
+                task.due_date = Some(truncate_to_minute(due_date.naive_local()));
+            }
+            if let Some(parent) = parent {
+                manager
+                    .validate_parent(id, parent)
+                    .map_err(|e| format!("Error creating task: {}", e))?;
+                task.parent = Some(parent);
+            }
+            if task.is_due_in_past() {
+                opts.warn("Warning: due date is in the past");
+            }
+            let preview = format!("Would add task {}: {}", task.id, task.title);
+            manager
+                .add_task(task)
+                .map_err(|e| format!("Error adding task: {}", e))?;
+            opts.report("Task added successfully!", &preview);
         }
         Commands::List {
-            category,
-            priority,
+            filter,
             completed,
             pending,
+            all,
+            due_before,
+            due_after,
+            created_before,
+            created_after,
+            completed_before,
+            completed_after,
+            min_priority,
+            no_due,
+            has_due,
+            group_by,
+            by_date,
+            columns,
+            sort,
+            reverse,
+            recent,
+            relative,
+            tree,
+            output,
+            page,
         } => {
+            let format = opts
+                .output_format(false)
+                .map_err(|e| format!("Error listing tasks: {}", e))?;
+            let parse_bound = |bound: Option<String>| {
+                bound
+                    .as_deref()
+                    .map(parse_due_date)
+                    .transpose()
+                    .map_err(|e| format!("Error listing tasks: {}", e))
+            };
+            let completed = completed || completed_before.is_some() || completed_after.is_some();
             let filters = ListFilters {
-                category,
-                priority,
                 completed,
-                pending,
+                pending: pending || !(completed || all),
+                due_before: parse_bound(due_before)?,
+                due_after: parse_bound(due_after)?,
+                created_before: parse_bound(created_before)?,
+                created_after: parse_bound(created_after)?,
+                completed_before: parse_bound(completed_before)?,
+                completed_after: parse_bound(completed_after)?,
+                min_priority: min_priority
+                    .as_deref()
+                    .map(Priority::parse)
+                    .transpose()
+                    .map_err(|e| format!("Error listing tasks: {}", e))?,
+                has_due: if no_due {
+                    Some(false)
+                } else if has_due {
+                    Some(true)
+                } else {
+                    None
+                },
+                ..filter.into()
+            };
+            let mut tasks = manager.list_tasks(&filters);
+            let (sort, reverse) = if recent {
+                (Some(SortKey::Created), true)
+            } else {
+                (sort, reverse)
+            };
+            if let Some(key) = sort {
+                sort_tasks(&mut tasks, key, reverse);
+            }
+            let columns = if columns.is_empty() {
+                Column::ALL.to_vec()
+            } else {
+                columns
+            };
+            let count = tasks.len();
+            if let Some(contents) = render(format, Rendered::Tasks(&tasks))
+                .map_err(|e| format!("Error listing tasks: {}", e))?
+            {
+                match &output {
+                    Some(path) => {
+                        fs::write(path, contents)
+                            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                        opts.info(&format!("Wrote {} tasks to {}", count, path.display()));
+                    }
+                    None => print!("{}", contents),
+                }
+                return Ok(());
+            }
+            let blocked = manager.blocked_ids();
+            let mut pager = if page { spawn_pager() } else { None };
+            let mut out: Box<dyn Write> = match &output {
+                Some(path) => {
+                    colored::control::set_override(false);
+                    Box::new(
+                        fs::File::create(path)
+                            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?,
+                    )
+                }
+                None => match pager.as_mut().and_then(|p| p.stdin.take()) {
+                    Some(stdin) => Box::new(stdin),
+                    None => Box::new(io::stdout()),
+                },
+            };
+            let group_by = if by_date {
+                Some("date".to_string())
+            } else {
+                group_by
             };
-            display_tasks(manager.list_tasks(&filters));
+            let summary = task_summary(&tasks).filter(|_| opts.shows_summary());
+            let written = match group_by.as_deref() {
+                _ if tree => write_task_tree(&mut out, tasks, &manager.tasks, &blocked),
+                Some(group_by) => {
+                    write_grouped_tasks(&mut out, tasks, &columns, group_by, &blocked, relative)
+                }
+                None => write_tasks(&mut out, tasks, &columns, &blocked, relative),
+            }
+            .and_then(|_| match &summary {
+                Some(summary) => writeln!(out, "{}", summary),
+                None => Ok(()),
+            });
+            drop(out);
+            if let Some(mut pager) = pager {
+                let _ = pager.wait();
+            }
+            if let Some(path) = output {
+                apply_output_mode(opts.plain);
+                written.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                opts.info(&format!("Wrote {} tasks to {}", count, path.display()));
+            }
         }
-        Commands::Show { id } => match manager.show_task(id) {
-            Ok(task) => display_task_details(task),
-            Err(e) => eprintln!("Error showing task: {}", e),
-        },
-        Commands::Complete { id } => {
-            if let Err(e) = manager.complete_task(id) {
-                eprintln!("Error completing task: {}", e);
+        Commands::Ids {
+            filter,
+            completed,
+            pending,
+            inline,
+        } => {
+            let filters = ListFilters {
+                completed,
+                pending,
+                ..filter.into()
+            };
+            let ids: Vec<String> = manager
+                .list_tasks(&filters)
+                .iter()
+                .map(|t| t.id.to_string())
+                .collect();
+            if inline {
+                println!("{}", ids.join(" "));
             } else {
-                println!("Task completed successfully!");
+                for id in ids {
+                    println!("{}", id);
+                }
             }
         }
-        Commands::Remove { id } => {
-            if let Err(e) = manager.remove_task(id) {
-                eprintln!("Error removing task: {}", e);
+        Commands::Search {
+            query,
+            fuzzy,
+            case_sensitive,
+            sort,
+            reverse,
+        } => {
+            let mut tasks = if fuzzy {
+                manager.fuzzy_search_tasks(&query, FUZZY_SEARCH_LIMIT, case_sensitive)
             } else {
-                println!("Task removed successfully!");
+                manager.search_tasks(&query, case_sensitive)
+            };
+            if let Some(key) = sort {
+                sort_tasks(&mut tasks, key, reverse);
+            }
+            display_tasks(
+                tasks,
+                &Column::ALL,
+                &manager.blocked_ids(),
+                opts.shows_summary(),
+            );
+        }
+        Commands::Stats { json, field } => {
+            let format = opts
+                .output_format(json)
+                .map_err(|e| format!("Error showing stats: {}", e))?;
+            let stats = Stats::from_tasks(&manager.tasks);
+            if let Some(field) = field {
+                if opts.format.is_some() {
+                    return Err(
+                        "Error showing stats: --field can't be combined with --format".to_string(),
+                    );
+                }
+                let value = stats
+                    .field(&field)
+                    .map_err(|e| format!("Error showing stats: {}", e))?;
+                println!("{}", value);
+                return Ok(());
+            }
+            match render(format, Rendered::Stats(&stats))
+                .map_err(|e| format!("Error serializing stats: {}", e))?
+            {
+                Some(contents) => print!("{}", contents),
+                None => display_stats(&stats),
+            }
+        }
+        Commands::Today => {
+            let end_of_day = Local::now()
+                .date_naive()
+                .and_hms_opt(23, 59, 59)
+                .expect("valid time");
+            let tasks: Vec<&Task> = manager
+                .tasks
+                .iter()
+                .filter(|t| !t.completed && t.due_date.is_some_and(|d| d <= end_of_day))
+                .collect();
+            let planned: u32 = tasks.iter().filter_map(|t| t.estimate).sum();
+            let has_estimates = tasks.iter().any(|t| t.estimate.is_some());
+            display_tasks(
+                tasks,
+                &Column::ALL,
+                &manager.blocked_ids(),
+                opts.shows_summary(),
+            );
+            if has_estimates {
+                println!("Planned: {}.", format_minutes(planned));
+            }
+        }
+        Commands::Last => match manager.tasks.iter().max_by_key(|t| t.created_at) {
+            Some(task) => {
+                println!("{}", format!("Task {}", task.id).bold());
+                display_task_details(task);
+            }
+            None => println!("No tasks yet."),
+        },
+        Commands::Show { task, json } => {
+            let format = opts
+                .output_format(json)
+                .map_err(|e| format!("Error showing task: {}", e))?;
+            let task = manager
+                .find_task(&task, false)
+                .map_err(|e| format!("Error showing task: {}", e))?;
+            match render(format, Rendered::Task(task))
+                .map_err(|e| format!("Error serializing task: {}", e))?
+            {
+                Some(contents) => print!("{}", contents),
+                None => display_task_details(task),
+            }
+        }
+        Commands::Complete { ids, filter, yes } => {
+            if !ids.is_empty() {
+                let ids = ids
+                    .iter()
+                    .map(|query| {
+                        manager
+                            .find_task(query, true)
+                            .map(|t| (t.id, t.title.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Error completing task: {}", e))?;
+                for (id, title) in ids {
+                    manager
+                        .complete_task(id)
+                        .map_err(|e| format!("Error completing task: {}", e))?;
+                    opts.report(
+                        "Task completed successfully!",
+                        &format!("Would complete task {}: {}", id, title),
+                    );
+                }
+                return Ok(());
+            }
+
+            if filter.is_empty() {
+                return Err(
+                    "Error completing tasks: specify task ids or at least one of --category, --priority, --tag"
+                        .to_string(),
+                );
+            }
+            let filters = ListFilters {
+                pending: true,
+                ..filter.into()
+            };
+            let count = manager.list_tasks(&filters).len();
+            if count == 0 {
+                opts.info("No pending tasks match the given filters.");
+                return Ok(());
             }
+            if !opts.dry_run && !yes && !confirm(&format!("Complete {} matching task(s)?", count)) {
+                opts.info("Aborted.");
+                return Ok(());
+            }
+            let count = manager
+                .complete_matching(&filters)
+                .map_err(|e| format!("Error completing tasks: {}", e))?;
+            opts.report(
+                &format!("Completed {} task(s).", count),
+                &format!("Would complete {} task(s)", count),
+            );
+        }
+        Commands::Remove { id: Some(id), .. } => {
+            let task = manager
+                .remove_task(id)
+                .map_err(|e| format!("Error removing task: {}", e))?;
+            opts.report(
+                "Task removed successfully!",
+                &format!("Would remove task {}: {}", task.id, task.title),
+            );
+        }
+        Commands::Remove {
+            id: None,
+            filter,
+            yes,
+            force,
+        } => {
+            if filter.is_empty() {
+                return Err(
+                    "Error removing tasks: specify a task id or at least one of --category, --priority, --tag"
+                        .to_string(),
+                );
+            }
+            let filters = ListFilters::from(filter);
+            let count = manager.list_tasks(&filters).len();
+            if count == 0 {
+                opts.info("No tasks match the given filters.");
+                return Ok(());
+            }
+            if !opts.dry_run {
+                check_mass_delete(count, force)?;
+            }
+            if !opts.dry_run && !yes && !confirm(&format!("Remove {} matching task(s)?", count)) {
+                opts.info("Aborted.");
+                return Ok(());
+            }
+            let removed = manager
+                .remove_matching(&filters)
+                .map_err(|e| format!("Error removing tasks: {}", e))?;
+            let titles: Vec<String> = removed
+                .iter()
+                .map(|t| format!("  {}: {}", t.id, t.title))
+                .collect();
+            opts.report(
+                &format!("Removed {} task(s):\n{}", removed.len(), titles.join("\n")),
+                &format!(
+                    "Would remove {} task(s):\n{}",
+                    removed.len(),
+                    titles.join("\n")
+                ),
+            );
+        }
+        Commands::Purge {
+            older_than,
+            yes,
+            force,
+        } => {
+            let age = parse_non_negative_duration(&older_than)
+                .map_err(|e| format!("Error purging tasks: {}", e))?;
+            let cutoff = Local::now()
+                .checked_sub_signed(age)
+                .ok_or_else(|| format!("Error purging tasks: '{}' is too far back", older_than))?;
+            let count = manager.purgeable(cutoff).count();
+            if count == 0 {
+                opts.info("No completed tasks to purge.");
+                return Ok(());
+            }
+            if !opts.dry_run {
+                check_mass_delete(count, force)?;
+            }
+            if !opts.dry_run && !yes && !confirm(&format!("Purge {} completed task(s)?", count)) {
+                opts.info("Aborted.");
+                return Ok(());
+            }
+            let purged = manager
+                .purge_completed(cutoff)
+                .map_err(|e| format!("Error purging tasks: {}", e))?;
+            opts.report(
+                &format!("Purged {} completed task(s).", purged.len()),
+                &format!("Would purge {} completed task(s).", purged.len()),
+            );
         }
         Commands::Edit {
             id,
@@ -483,7 +3720,31 @@ fn main() {
             priority,
             category,
             tags,
+            tag,
+            edit,
+            blocked_by,
+            no_blocked_by,
+            parent,
+            no_parent,
+            diff,
+            estimate,
         } => {
+            let tags = combine_tags(tags, tag);
+            let description = if edit {
+                let current = manager
+                    .show_task(id)
+                    .map_err(|e| format!("Error editing task: {}", e))?
+                    .description
+                    .clone()
+                    .unwrap_or_default();
+                Some(edit_in_editor(&current).map_err(|e| format!("Error editing task: {}", e))?)
+            } else {
+                description
+            };
+            let due_changed = due.is_some();
+            if let Some(category) = &category {
+                warn_category_typo(opts, manager, category);
+            }
             let updates = TaskUpdates {
                 title,
                 description,
@@ -491,12 +3752,977 @@ fn main() {
                 priority,
                 category,
                 tags,
+                blocked_by: if no_blocked_by {
+                    Some(Vec::new())
+                } else {
+                    Some(blocked_by).filter(|b| !b.is_empty())
+                },
+                estimate,
+                parent: if no_parent {
+                    Some(None)
+                } else {
+                    parent.map(Some)
+                },
+            };
+            if diff {
+                let edited = manager
+                    .preview_edit(id, updates)
+                    .map_err(|e| format!("Error editing task: {}", e))?;
+                let changes = describe_changes(manager.show_task(id)?, &edited);
+                if changes.is_empty() {
+                    opts.info("No changes.");
+                    return Ok(());
+                }
+                for change in &changes {
+                    println!("{}", change);
+                }
+                if opts.dry_run || !confirm("Save these changes?") {
+                    opts.info("Not saved.");
+                    return Ok(());
+                }
+                manager
+                    .replace_task(edited)
+                    .map_err(|e| format!("Error editing task: {}", e))?;
+            } else {
+                manager
+                    .edit_task(id, updates)
+                    .map_err(|e| format!("Error editing task: {}", e))?;
+            }
+            let task = manager.show_task(id)?;
+            if due_changed && task.is_due_in_past() {
+                opts.warn("Warning: due date is in the past");
+            }
+            opts.report(
+                "Task updated successfully!",
+                &format!("Would update task {}: {}", task.id, task.title),
+            );
+        }
+        Commands::Bump { id } => {
+            let priority = manager
+                .set_priority_with(id, Priority::raised)
+                .map_err(|e| format!("Error bumping task: {}", e))?;
+            opts.report(
+                &format!("Task {} is now {:?}.", id, priority),
+                &format!("Would set task {} to {:?}", id, priority),
+            );
+        }
+        Commands::Lower { id } => {
+            let priority = manager
+                .set_priority_with(id, Priority::lowered)
+                .map_err(|e| format!("Error lowering task: {}", e))?;
+            opts.report(
+                &format!("Task {} is now {:?}.", id, priority),
+                &format!("Would set task {} to {:?}", id, priority),
+            );
+        }
+        Commands::Rename { id, title } => {
+            let updates = TaskUpdates {
+                title: Some(title),
+                ..Default::default()
+            };
+            manager
+                .edit_task(id, updates)
+                .map_err(|e| format!("Error renaming task: {}", e))?;
+            let task = manager.show_task(id)?;
+            opts.report(
+                &format!("Task {} renamed to '{}'.", id, task.title),
+                &format!("Would rename task {} to '{}'", id, task.title),
+            );
+        }
+        Commands::Note { id, text } => {
+            manager
+                .add_note(id, &text)
+                .map_err(|e| format!("Error adding note: {}", e))?;
+            opts.report(
+                &format!("Note added to task {}.", id),
+                &format!("Would add a note to task {}", id),
+            );
+        }
+        Commands::Pin { id } => {
+            manager
+                .set_pinned(id, true)
+                .map_err(|e| format!("Error pinning task: {}", e))?;
+            opts.report(
+                &format!("Task {} pinned.", id),
+                &format!("Would pin task {}", id),
+            );
+        }
+        Commands::Unpin { id } => {
+            manager
+                .set_pinned(id, false)
+                .map_err(|e| format!("Error unpinning task: {}", e))?;
+            opts.report(
+                &format!("Task {} unpinned.", id),
+                &format!("Would unpin task {}", id),
+            );
+        }
+        Commands::Focus { id, minutes } => {
+            let task = manager
+                .show_task(id)
+                .map_err(|e| format!("Error starting focus session: {}", e))?;
+            let completed = task.completed;
+            display_task_details(task);
+            println!();
+            run_focus_timer(minutes).map_err(|e| format!("Error running timer: {}", e))?;
+            let total = manager
+                .add_time_spent(id, u32::try_from(minutes).unwrap_or(u32::MAX))
+                .map_err(|e| format!("Error logging time: {}", e))?;
+            println!(
+                "Focus session over. Time spent on this task: {}.",
+                format_minutes(total)
+            );
+            if !completed && confirm(&format!("Mark task {} as done?", id)) {
+                manager
+                    .complete_task(id)
+                    .map_err(|e| format!("Error completing task: {}", e))?;
+                opts.report(
+                    "Task completed successfully!",
+                    &format!("Would complete task {}", id),
+                );
+            }
+        }
+        Commands::AddInteractive => {
+            let task = prompt_task(manager.next_id())
+                .map_err(|e| format!("Error creating task: {}", e))?;
+            warn_category_typo(opts, manager, task.category.name());
+            if task.is_due_in_past() {
+                opts.warn("Warning: due date is in the past");
+            }
+            let id = task.id;
+            manager
+                .add_task(task)
+                .map_err(|e| format!("Error adding task: {}", e))?;
+            opts.report(
+                &format!("Added task {}.", id),
+                &format!("Would add task {}", id),
+            );
+        }
+        Commands::AddBatch => {
+            let mut tasks = Vec::new();
+            for (index, line) in io::stdin().lines().enumerate() {
+                let line = line.map_err(|e| format!("Error reading stdin: {}", e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_batch_line(manager.next_id() + tasks.len(), &line) {
+                    Ok(task) => tasks.push(task),
+                    Err(e) => opts.warn(&format!("Skipping line {}: {}", index + 1, e)),
+                }
+            }
+            let count = tasks.len();
+            manager
+                .add_tasks(tasks)
+                .map_err(|e| format!("Error adding tasks: {}", e))?;
+            opts.report(
+                &format!("Added {} task(s).", count),
+                &format!("Would add {} task(s)", count),
+            );
+        }
+        Commands::Duplicate { id } => {
+            let new_id = manager
+                .duplicate_task(id)
+                .map_err(|e| format!("Error duplicating task: {}", e))?;
+            opts.report(
+                &format!("Task {} duplicated as task {}.", id, new_id),
+                &format!("Would duplicate task {} as task {}", id, new_id),
+            );
+        }
+        Commands::Snooze { id, by } => {
+            let by = parse_non_negative_duration(&by)
+                .map_err(|e| format!("Error snoozing task: {}", e))?;
+            let due_date = manager
+                .snooze_task(id, by)
+                .map_err(|e| format!("Error snoozing task: {}", e))?;
+            let due_date = due_date.format(config().date_format());
+            opts.report(
+                &format!("Task {} is now due {}.", id, due_date),
+                &format!("Would move task {} to {}", id, due_date),
+            );
+        }
+        Commands::Merge { other } => {
+            let tasks =
+                read_task_file(&other).map_err(|e| format!("Error merging tasks: {}", e))?;
+            let (added, skipped) = manager
+                .merge_tasks(tasks)
+                .map_err(|e| format!("Error merging tasks: {}", e))?;
+            opts.report(
+                &format!(
+                    "Merged {}: {} added, {} skipped as duplicates.",
+                    other.display(),
+                    added,
+                    skipped
+                ),
+                &format!(
+                    "Would merge {}: {} added, {} skipped as duplicates",
+                    other.display(),
+                    added,
+                    skipped
+                ),
+            );
+        }
+        Commands::Import { file, preview } => {
+            let csv = match opts.format {
+                None | Some(OutputFormat::Json) => false,
+                Some(OutputFormat::Csv) => true,
+                Some(format) => {
+                    return Err(format!(
+                        "Error importing tasks: can't import {}, only json or csv",
+                        format.name()
+                    ))
+                }
+            };
+            let mut skipped = None;
+            let tasks = if csv {
+                let contents = fs::read_to_string(&file)
+                    .map_err(|e| format!("Error importing tasks: {}", e))?;
+                let rows = export::from_csv(&contents)
+                    .map_err(|e| format!("Error importing tasks: {}", e))?;
+                let mut tasks = Vec::new();
+                let mut skipped_rows = 0;
+                for (row, result) in rows {
+                    match result {
+                        Ok(task) => tasks.push(task),
+                        Err(e) => {
+                            opts.warn(&format!("Skipping row {}: {}", row, e));
+                            skipped_rows += 1;
+                        }
+                    }
+                }
+                skipped = Some(skipped_rows);
+                tasks
+            } else {
+                read_task_file(&file).map_err(|e| format!("Error importing tasks: {}", e))?
+            };
+            let skipped = skipped
+                .map(|count| format!(" Skipped {} invalid row(s).", count))
+                .unwrap_or_default();
+            if preview {
+                let plan = manager.import_plan(&tasks);
+                println!(
+                    "Would import {} task(s) from {}:",
+                    plan.len(),
+                    file.display()
+                );
+                for ((old, new), task) in plan.iter().zip(&tasks) {
+                    println!("  {} -> {}: {}", old, new, task.title);
+                }
+                if !skipped.is_empty() {
+                    println!("{}", skipped.trim_start());
+                }
+                return Ok(());
+            }
+            let plan = manager
+                .import_tasks(tasks)
+                .map_err(|e| format!("Error importing tasks: {}", e))?;
+            opts.report(
+                &format!(
+                    "Imported {} task(s) from {}.{}",
+                    plan.len(),
+                    file.display(),
+                    skipped
+                ),
+                &format!(
+                    "Would import {} task(s) from {}.{}",
+                    plan.len(),
+                    file.display(),
+                    skipped
+                ),
+            );
+        }
+        Commands::TagAll { name, filter } => {
+            let count = manager
+                .tag_matching(&name, &filter.into())
+                .map_err(|e| format!("Error tagging tasks: {}", e))?;
+            opts.report(
+                &format!("Tagged {} task(s) with '{}'.", count, name),
+                &format!("Would tag {} task(s) with '{}'", count, name),
+            );
+        }
+        Commands::RenameTag { from, to } => {
+            let count = manager
+                .rename_tag(&from, &to)
+                .map_err(|e| format!("Error renaming tag: {}", e))?;
+            opts.report(
+                &format!("Renamed tag '{}' to '{}' on {} task(s).", from, to, count),
+                &format!(
+                    "Would rename tag '{}' to '{}' on {} task(s)",
+                    from, to, count
+                ),
+            );
+        }
+        Commands::MoveCategory { from, to, pending } => {
+            let count = manager
+                .move_category(&from, &to, pending)
+                .map_err(|e| format!("Error moving tasks: {}", e))?;
+            opts.report(
+                &format!("Moved {} task(s) from '{}' to '{}'.", count, from, to),
+                &format!("Would move {} task(s) from '{}' to '{}'", count, from, to),
+            );
+        }
+        Commands::RenameCategory { from, to } => {
+            let count = manager
+                .rename_category(&from, &to)
+                .map_err(|e| format!("Error renaming category: {}", e))?;
+            opts.report(
+                &format!("Moved {} task(s) from '{}' to '{}'.", count, from, to),
+                &format!("Would move {} task(s) from '{}' to '{}'", count, from, to),
+            );
+        }
+        Commands::Renumber => {
+            let changes = manager
+                .renumber()
+                .map_err(|e| format!("Error renumbering tasks: {}", e))?;
+            if changes.is_empty() {
+                opts.info("Task ids are already contiguous.");
+                return Ok(());
+            }
+            let mapping: Vec<String> = changes
+                .iter()
+                .map(|(old, new)| format!("  {} -> {}", old, new))
+                .collect();
+            opts.report(
+                &format!(
+                    "Renumbered {} task(s):\n{}",
+                    changes.len(),
+                    mapping.join("\n")
+                ),
+                &format!(
+                    "Would renumber {} task(s):\n{}",
+                    changes.len(),
+                    mapping.join("\n")
+                ),
+            );
+        }
+        Commands::Export {
+            output,
+            completed,
+            pending,
+            since_id,
+            min_id,
+            max_id,
+        } => {
+            if let (Some(min), Some(max)) = (min_id, max_id) {
+                if min > max {
+                    return Err(format!(
+                        "Error exporting tasks: --min-id {} is greater than --max-id {}",
+                        min, max
+                    ));
+                }
+            }
+            let filters = ListFilters {
+                completed,
+                pending,
+                ..Default::default()
+            };
+            let mut tasks = manager.list_tasks(&filters);
+            if let Some(since_id) = since_id {
+                tasks.retain(|t| t.id > since_id);
+            }
+            tasks.retain(|t| {
+                min_id.is_none_or(|min| t.id >= min) && max_id.is_none_or(|max| t.id <= max)
+            });
+            let export_error = |e| format!("Error exporting tasks: {}", e);
+            let contents = match opts.format.unwrap_or(OutputFormat::Json) {
+                OutputFormat::Html => export::to_html(&tasks),
+                OutputFormat::Csv => export::to_csv(&tasks),
+                OutputFormat::Markdown => export::to_markdown(&tasks),
+                OutputFormat::Ics => export::to_ics(&tasks),
+                OutputFormat::Jsonl => export::to_jsonl(&tasks).map_err(export_error)?,
+                OutputFormat::Json => export::to_json(&tasks).map_err(export_error)?,
+                OutputFormat::Table => {
+                    return Err("Error exporting tasks: table isn't an export format".to_string())
+                }
+            };
+            match output {
+                Some(path) => {
+                    fs::write(&path, contents)
+                        .map_err(|e| format!("Error exporting tasks: {}", e))?;
+                    opts.info(&format!(
+                        "Exported {} task(s) to {}",
+                        tasks.len(),
+                        path.display()
+                    ));
+                }
+                None => print!("{}", contents),
+            }
+        }
+        #[cfg(feature = "notifications")]
+        Commands::Notify => {
+            let horizon = Local::now().naive_local() + Duration::hours(1);
+            let due: Vec<&Task> = manager
+                .tasks
+                .iter()
+                .filter(|t| !t.completed && t.due_date.is_some_and(|d| d <= horizon))
+                .collect();
+            for task in &due {
+                let due_date = task
+                    .due_date
+                    .map(|d| d.format(config().date_format()).to_string())
+                    .unwrap_or_default();
+                println!("{}: {} (due {})", task.id, task.title, due_date);
+                if let Err(e) = notify_rust::Notification::new()
+                    .summary(&task.title)
+                    .body(&format!("Due {}", due_date))
+                    .show()
+                {
+                    opts.warn(&format!(
+                        "Failed to send notification for task {}: {}",
+                        task.id, e
+                    ));
+                }
+            }
+            println!("{} task(s) due within the next hour or overdue.", due.len());
+        }
+        #[cfg(feature = "watch")]
+        Commands::Watch { filter } => {
+            let filters = ListFilters {
+                pending: true,
+                ..filter.into()
+            };
+            run_watch(manager, &filters, opts.shows_summary())
+                .map_err(|e| format!("Error watching tasks: {}", e))?;
+        }
+        #[cfg(feature = "encrypt")]
+        Commands::SetPassword { remove } => {
+            if cfg!(feature = "sqlite") {
+                return Err(
+                    "Error setting password: encryption is only supported for the JSON task file"
+                        .to_string(),
+                );
+            }
+            // The archive follows the task file, so re-save it with the new passphrase.
+            let archive = manager
+                .archive_path()
+                .exists()
+                .then(|| manager.load_archive())
+                .transpose()
+                .map_err(|e| format!("Error setting password: {}", e))?;
+            manager.passphrase = if remove {
+                None
+            } else {
+                Some(
+                    crypto::new_passphrase()
+                        .map_err(|e| format!("Error setting password: {}", e))?,
+                )
             };
-            if let Err(e) = manager.edit_task(id, updates) {
-                eprintln!("Error editing task: {}", e);
+            manager
+                .save()
+                .map_err(|e| format!("Error setting password: {}", e))?;
+            if let Some(archive) = archive {
+                manager
+                    .save_archive(&archive)
+                    .map_err(|e| format!("Error setting password: {}", e))?;
+            }
+            let message = if remove {
+                "Task file is no longer encrypted."
             } else {
-                println!("Task updated successfully!");
+                "Task file encrypted."
+            };
+            opts.report(message, "Would re-save the task file (dry run)");
+        }
+        Commands::Progress => display_progress(&manager.tasks),
+        Commands::Legend => display_legend(),
+        Commands::Report { since } => {
+            let since =
+                parse_due_date(&since).map_err(|e| format!("Error building report: {}", e))?;
+            let archive = manager
+                .load_archive()
+                .map_err(|e| format!("Error building report: {}", e))?;
+            let tasks: Vec<&Task> = manager.tasks.iter().chain(archive.iter()).collect();
+            display_completion_report(&tasks, since);
+        }
+        Commands::InitConfig => {
+            let path = Config::path();
+            if path.exists() {
+                return Err(format!(
+                    "Error writing config: {} already exists",
+                    path.display()
+                ));
+            }
+            fs::write(&path, CONFIG_TEMPLATE)
+                .map_err(|e| format!("Error writing config: {}", e))?;
+            opts.info(&format!("Wrote config template to {}", path.display()));
+        }
+        Commands::Repl => run_repl(manager, opts),
+        Commands::Archive => {
+            let count = manager
+                .archive_completed()
+                .map_err(|e| format!("Error archiving tasks: {}", e))?;
+            opts.report(
+                &format!("Archived {} completed task(s).", count),
+                &format!("Would archive {} completed task(s)", count),
+            );
+        }
+        Commands::SaveTemplate {
+            name,
+            title,
+            description,
+            priority,
+            category,
+            tags,
+            tag,
+            estimate,
+        } => {
+            let name = name.trim().to_string();
+            if name.is_empty() {
+                return Err("Error saving template: name cannot be empty".to_string());
+            }
+            let priority = priority.as_deref().unwrap_or(config().default_priority());
+            let category = category.as_deref().unwrap_or(config().default_category());
+            let skeleton = Task::new(
+                0,
+                title,
+                description,
+                None,
+                priority,
+                category,
+                combine_tags(tags, tag),
+            )
+            .map_err(|e| format!("Error saving template: {}", e))?;
+            let estimate = estimate
+                .as_deref()
+                .map(parse_estimate)
+                .transpose()
+                .map_err(|e| format!("Error saving template: {}", e))?;
+
+            let path = manager.templates_path();
+            let mut templates =
+                template::load(&path).map_err(|e| format!("Error saving template: {}", e))?;
+            let replaced = templates
+                .insert(
+                    name.clone(),
+                    template::Template::from_task(&skeleton, estimate),
+                )
+                .is_some();
+            if !opts.dry_run {
+                template::save(&path, &templates)
+                    .map_err(|e| format!("Error saving template: {}", e))?;
+            }
+            let verb = if replaced { "Updated" } else { "Saved" };
+            opts.report(
+                &format!("{} template '{}'.", verb, name),
+                &format!("Would save template '{}'", name),
+            );
+        }
+        Commands::AddFromTemplate { name, title } => {
+            let templates = template::load(&manager.templates_path())
+                .map_err(|e| format!("Error adding task: {}", e))?;
+            let template = templates
+                .get(name.trim())
+                .ok_or_else(|| format!("Error adding task: no template named '{}'", name))?;
+            let task = template
+                .instantiate(manager.next_id(), title)
+                .map_err(|e| format!("Error creating task: {}", e))?;
+            let id = task.id;
+            let preview = format!("Would add task {}: {}", id, task.title);
+            manager
+                .add_task(task)
+                .map_err(|e| format!("Error adding task: {}", e))?;
+            opts.report(&format!("Added task {}.", id), &preview);
+        }
+        Commands::Templates => {
+            let templates = template::load(&manager.templates_path())
+                .map_err(|e| format!("Error loading templates: {}", e))?;
+            if templates.is_empty() {
+                println!("No templates saved.");
+                return Ok(());
+            }
+            let mut table = new_table();
+            table.add_row(Row::new(
+                ["Name", "Title", "Priority", "Category", "Tags", "Estimate"]
+                    .iter()
+                    .map(|h| Cell::new(h))
+                    .collect(),
+            ));
+            for (name, template) in &templates {
+                table.add_row(Row::new(vec![
+                    Cell::new(name),
+                    Cell::new(&template.title),
+                    Cell::new(&template.priority.colored().to_string()),
+                    Cell::new(&template.category.colored().to_string()),
+                    Cell::new(&colored_tags(&template.tags)),
+                    Cell::new(&template.estimate.map(format_minutes).unwrap_or_default()),
+                ]));
+            }
+            table.printstd();
+        }
+        Commands::Archived => {
+            let archive = manager
+                .load_archive()
+                .map_err(|e| format!("Error loading archive: {}", e))?;
+            display_tasks(
+                archive.iter().collect(),
+                &Column::ALL,
+                &HashSet::new(),
+                opts.shows_summary(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Redraws the filtered task list every time the storage file changes, until
+/// interrupted with Ctrl-C.
+#[cfg(feature = "watch")]
+fn run_watch(
+    manager: &mut TodoManager,
+    filters: &ListFilters,
+    summary: bool,
+) -> Result<(), String> {
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc;
+
+    let path = manager.storage_path();
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("Failed to start watcher: {}", e))?;
+    // Watch the directory rather than the file so atomic replaces are seen too.
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+
+    let render = |manager: &TodoManager| {
+        print!("\x1B[2J\x1B[1;1H");
+        println!("Watching {} (Ctrl-C to exit)\n", path.display());
+        display_tasks(
+            manager.list_tasks(filters),
+            &Column::ALL,
+            &manager.blocked_ids(),
+            summary,
+        );
+        let _ = io::stdout().flush();
+    };
+    render(manager);
+
+    for event in &rx {
+        let event = event.map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+        if !event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == path.file_name())
+        {
+            continue;
+        }
+        // A single save usually fires several events; give it a moment to settle.
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        while rx.try_recv().is_ok() {}
+
+        if let Err(e) = manager.reload() {
+            eprintln!("{}", e);
+            continue;
+        }
+        render(manager);
+    }
+    Ok(())
+}
+
+fn run_repl(manager: &mut TodoManager, opts: &GlobalOpts) {
+    opts.info("Interactive mode. Type 'help' for commands, 'quit' to exit.");
+    let stdin = io::stdin();
+    loop {
+        print!("todo> ");
+        if io::stdout().flush().is_err() {
+            break;
+        }
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "quit" || line == "exit" {
+            break;
+        }
+
+        let Some(words) = shlex::split(line) else {
+            eprintln!("Error: unbalanced quotes");
+            continue;
+        };
+        let cli = match Cli::try_parse_from(std::iter::once("todo".to_string()).chain(words)) {
+            Ok(cli) => cli,
+            Err(e) => {
+                let _ = e.print();
+                continue;
             }
+        };
+        if matches!(cli.command, Commands::Repl) {
+            eprintln!("Error: already in interactive mode");
+            continue;
+        }
+
+        let line_opts = GlobalOpts {
+            quiet: opts.quiet || cli.global.quiet,
+            dry_run: opts.dry_run || cli.global.dry_run,
+            verbose: opts.verbose || cli.global.verbose,
+            plain: opts.plain || cli.global.plain,
+            strict_date: opts.strict_date || cli.global.strict_date,
+            compact: opts.compact || cli.global.compact,
+            format: cli.global.format.or(opts.format),
+        };
+        STRICT_DATES.store(line_opts.strict_date, atomic::Ordering::Relaxed);
+        COMPACT_JSON.store(line_opts.compact, atomic::Ordering::Relaxed);
+        apply_output_mode(line_opts.plain);
+        manager.dry_run = line_opts.dry_run;
+        manager.verbose = line_opts.verbose;
+        // A dry run still changes the in-memory tasks; undo that so a later
+        // line without --dry-run doesn't save it.
+        let snapshot = line_opts
+            .dry_run
+            .then(|| (manager.tasks.clone(), manager.next_id));
+        if let Err(e) = run(cli.command, manager, &line_opts) {
+            eprintln!("{}", e);
+        }
+        if let Some((tasks, next_id)) = snapshot {
+            manager.tasks = tasks;
+            manager.next_id = next_id;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags_lowercases_trims_and_dedups() {
+        assert_eq!(parse_tags("work, Work,  urgent,"), vec!["work", "urgent"]);
+    }
+
+    #[test]
+    fn humanize_duration_picks_singular_or_plural() {
+        assert_eq!(humanize_duration(Duration::days(1)), "1 day");
+        assert_eq!(humanize_duration(Duration::days(3)), "3 days");
+        assert_eq!(humanize_duration(Duration::hours(1)), "1 hour");
+        assert_eq!(humanize_duration(Duration::minutes(5)), "5 minutes");
+        assert_eq!(humanize_duration(-Duration::hours(2)), "2 hours");
+        assert_eq!(
+            humanize_duration(Duration::seconds(30)),
+            "less than a minute"
+        );
+    }
+
+    #[test]
+    fn relative_due_describes_past_and_future() {
+        let now = Local::now().naive_local();
+        let future = now + Duration::days(2) + Duration::hours(1);
+        let past = now - Duration::days(1) - Duration::hours(1);
+        assert_eq!(relative_due(future, false), "in 2 days");
+        assert_eq!(relative_due(past, false), "overdue by 1 day");
+        assert_eq!(relative_due(past, true), "1 day ago");
+    }
+
+    #[test]
+    fn new_task_rejects_whitespace_only_title() {
+        let result = Task::new(1, "   ".to_string(), None, None, "medium", "personal", None);
+        assert_eq!(result.unwrap_err(), "Task title cannot be empty.");
+    }
+
+    #[test]
+    fn parse_duration_rejects_out_of_range_amounts() {
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+        assert!(parse_duration("99999999999999w").is_err());
+        assert!(parse_non_negative_duration("-1d").is_err());
+    }
+
+    fn task(id: usize, title: &str) -> Task {
+        Task::new(
+            id,
+            title.to_string(),
+            None,
+            None,
+            "medium",
+            "personal",
+            None,
+        )
+        .unwrap()
+    }
+
+    /// A manager over `tasks` whose storage lives in a fresh temporary directory.
+    fn test_manager(name: &str, tasks: Vec<Task>) -> TodoManager {
+        let dir = env::temp_dir().join(format!("todo-cli-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join(".todo-cli.json");
+        TodoManager {
+            tasks,
+            #[cfg(feature = "sqlite")]
+            store: sqlite::SqliteStore::open(&file_path.with_extension("db"), &file_path).unwrap(),
+            file_path,
+            dry_run: false,
+            verbose: false,
+            #[cfg(feature = "encrypt")]
+            passphrase: None,
+            next_id: 1,
+        }
+    }
+
+    #[test]
+    fn fuzzy_search_matches_despite_missing_letters() {
+        let manager = test_manager(
+            "fuzzy",
+            vec![task(1, "Send invoice"), task(2, "Water plants")],
+        );
+        let found = manager.fuzzy_search_tasks("invce", FUZZY_SEARCH_LIMIT, false);
+        let titles: Vec<&str> = found.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Send invoice"]);
+    }
+
+    #[test]
+    fn custom_categories_differing_in_case_share_a_stats_bucket() {
+        let mut first = task(1, "Mop floors");
+        first.category = Category::parse("Chores").unwrap();
+        let mut second = task(2, "Do dishes");
+        second.category = Category::parse(" chores").unwrap();
+        let stats = Stats::from_tasks(&[first, second]);
+        assert_eq!(stats.by_category.get("chores"), Some(&2));
+        assert_eq!(stats.by_category.len(), 1);
+    }
+
+    #[test]
+    fn list_rejects_completed_with_pending() {
+        assert!(Cli::try_parse_from(["todo", "list", "--completed", "--pending"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "list", "--completed"]).is_ok());
+    }
+
+    #[test]
+    fn tag_commands_match_legacy_mixed_case_tags() {
+        let mut legacy = task(1, "Write report");
+        legacy.tags = vec!["Work".to_string(), "Urgent".to_string()];
+        let mut manager = test_manager("legacy-tags", vec![legacy]);
+        manager.normalize_tags();
+
+        let filters = ListFilters::default();
+        assert_eq!(manager.tag_matching("urgent", &filters).unwrap(), 0);
+        assert_eq!(manager.tag_matching("q3", &filters).unwrap(), 1);
+        assert_eq!(manager.rename_tag("work", "job").unwrap(), 1);
+        assert_eq!(manager.tasks[0].tags, vec!["job", "urgent", "q3"]);
+    }
+
+    #[test]
+    fn task_file_path_falls_back_to_current_dir_without_home() {
+        let expected = env::current_dir().unwrap().join(".todo-cli.json");
+        assert_eq!(task_file_path(None, None), expected);
+        assert_eq!(
+            task_file_path(None, Some(PathBuf::from("/home/me"))),
+            PathBuf::from("/home/me/.todo-cli.json")
+        );
+        assert_eq!(
+            task_file_path(Some("/tmp/tasks.json".into()), None),
+            PathBuf::from("/tmp/tasks.json")
+        );
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_removal() {
+        let mut manager = test_manager("id-reuse", Vec::new());
+        for title in ["one", "two", "three"] {
+            let id = manager.next_id();
+            manager.add_task(task(id, title)).unwrap();
+        }
+        manager.remove_task(3).unwrap();
+        assert_eq!(manager.next_id(), 4);
+
+        let id = manager.next_id();
+        manager.add_task(task(id, "four")).unwrap();
+        assert_eq!(id, 4);
+
+        manager.remove_task(4).unwrap();
+        manager
+            .merge_tasks(vec![task(1, "merged"), task(2, "also merged")])
+            .unwrap();
+        let merged: Vec<usize> = manager.tasks.iter().skip(2).map(|t| t.id).collect();
+        assert_eq!(merged, vec![5, 6]);
+        assert_eq!(manager.next_id(), 7);
+    }
+
+    #[test]
+    fn search_ignores_case_unless_asked() {
+        let manager = test_manager(
+            "search-case",
+            vec![task(1, "Document the API"), task(2, "Lunch")],
+        );
+        let ids = |found: Vec<&Task>| found.iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(manager.search_tasks("api", false)), vec![1]);
+        assert_eq!(ids(manager.search_tasks("API", false)), vec![1]);
+        assert!(manager.search_tasks("api", true).is_empty());
+    }
+
+    #[test]
+    fn completing_twice_keeps_the_original_completion_time() {
+        let mut manager = test_manager("complete-twice", vec![task(1, "Pay rent")]);
+        manager.complete_task(1).unwrap();
+        let completed_at = manager.tasks[0].completed_at;
+        assert!(manager.complete_task(1).is_err());
+        assert_eq!(manager.tasks[0].completed_at, completed_at);
+    }
+
+    #[test]
+    fn renumber_skips_ids_held_by_archived_tasks() {
+        let mut manager = test_manager("renumber-archive", Vec::new());
+        for title in ["a", "b", "c", "d"] {
+            let id = manager.next_id();
+            manager.add_task(task(id, title)).unwrap();
+        }
+        manager.remove_task(2).unwrap();
+        manager.tasks[0].completed = true;
+        manager.archive_completed().unwrap();
+
+        // Task 1 is archived, so the remaining tasks start at 2.
+        let changes = manager.renumber().unwrap();
+        assert_eq!(changes, vec![(3, 2), (4, 3)]);
+        manager.tasks[0].completed = true;
+        manager.archive_completed().unwrap();
+
+        let mut ids: Vec<usize> = manager
+            .load_archive()
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn truncate_to_minute_drops_seconds() {
+        let at = NaiveDateTime::parse_from_str("2026-10-14 09:17:50.577", "%Y-%m-%d %H:%M:%S%.f")
+            .unwrap();
+        assert_eq!(truncate_to_minute(at).to_string(), "2026-10-14 09:17:00");
+    }
+
+    #[test]
+    fn completed_past_due_tasks_get_their_own_bucket() {
+        let now = Local::now().naive_local();
+        let mut done = task(1, "Filed taxes");
+        done.due_date = Some(now - Duration::days(2));
+        let mut late = done.clone();
+        done.completed = true;
+        late.id = 2;
+        assert_eq!(DueBucket::of(&done, now), DueBucket::Completed);
+        assert_eq!(DueBucket::of(&late, now), DueBucket::Overdue);
+    }
+
+    #[test]
+    fn format_is_accepted_before_or_after_the_subcommand() {
+        <Cli as clap::CommandFactory>::command().debug_assert();
+        for args in [
+            ["todo", "--format", "json", "list"],
+            ["todo", "list", "--format", "json"],
+        ] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert!(cli.global.format == Some(OutputFormat::Json));
         }
+        let cli = Cli::try_parse_from(["todo", "export", "-f", "csv"]).unwrap();
+        assert!(cli.global.format == Some(OutputFormat::Csv));
     }
 }
\ No newline at end of file
@@ -0,0 +1,369 @@
+use crate::{load_task_file, Category, Priority, Task};
+use chrono::{DateTime, Local, NaiveDateTime};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Task storage backed by a SQLite database, used when the `sqlite` feature is enabled.
+///
+/// Each task is one row keyed by its id. Saving compares the tasks with what was
+/// last loaded or saved and only writes the rows that were added, changed or
+/// removed.
+pub struct SqliteStore {
+    conn: Connection,
+    /// The JSON form of every row as last read or written, to detect changes.
+    saved: RefCell<HashMap<usize, String>>,
+}
+
+const CREATE_TASKS: &str = "CREATE TABLE IF NOT EXISTS tasks (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    description TEXT,
+    completed INTEGER NOT NULL,
+    completed_at TEXT,
+    created_at TEXT NOT NULL,
+    due_date TEXT,
+    priority TEXT NOT NULL,
+    category TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    blocked_by TEXT NOT NULL,
+    parent INTEGER,
+    estimate INTEGER,
+    pinned INTEGER NOT NULL,
+    notes TEXT NOT NULL,
+    time_spent_minutes INTEGER NOT NULL
+)";
+
+const COLUMNS: &str = "id, title, description, completed, completed_at, created_at, due_date, \
+    priority, category, tags, blocked_by, parent, estimate, pinned, notes, time_spent_minutes";
+
+const DUE_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `db_path`. When the database is created
+    /// for the first time and a JSON task file exists at `json_path`, its tasks are
+    /// imported once. Databases from before tasks had their own columns are
+    /// converted in place.
+    pub fn open(db_path: &Path, json_path: &Path) -> Result<Self, String> {
+        let is_new = !db_path.exists();
+        let conn =
+            Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+        let legacy = load_legacy_tasks(&conn)?;
+        if legacy.is_none() {
+            conn.execute(CREATE_TASKS, [])
+                .map_err(|e| format!("Failed to create tasks table: {}", e))?;
+        }
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value INTEGER NOT NULL)",
+            [],
+        )
+        .map_err(|e| format!("Failed to create meta table: {}", e))?;
+
+        let store = SqliteStore {
+            conn,
+            saved: RefCell::new(HashMap::new()),
+        };
+        if let Some(mut tasks) = legacy {
+            let next_id = store.load_next_id()?;
+            let next_id = assign_unique_ids(&mut tasks, next_id);
+            store.convert_legacy(&tasks, next_id)?;
+        } else if is_new && json_path.exists() {
+            let count = store.import_json(json_path)?;
+            eprintln!(
+                "Imported {} task(s) from {} into {}",
+                count,
+                json_path.display(),
+                db_path.display()
+            );
+        }
+        Ok(store)
+    }
+
+    pub fn load(&self) -> Result<Vec<Task>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM tasks ORDER BY id", COLUMNS))
+            .map_err(|e| format!("Failed to query tasks: {}", e))?;
+        let tasks = stmt
+            .query_map([], task_from_row)
+            .map_err(|e| format!("Failed to query tasks: {}", e))?
+            .collect::<Result<Vec<Task>, _>>()
+            .map_err(|e| format!("Failed to read task: {}", e))?;
+
+        let mut saved = self.saved.borrow_mut();
+        saved.clear();
+        for task in &tasks {
+            saved.insert(task.id, snapshot(task)?);
+        }
+        Ok(tasks)
+    }
+
+    /// The stored id counter, or 0 for databases created before it existed.
+    pub fn load_next_id(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = 'next_id'", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|id| id as usize)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(0),
+                e => Err(format!("Failed to read id counter: {}", e)),
+            })
+    }
+
+    /// Writes the rows that differ from the last load or save: removed tasks are
+    /// deleted, changed ones updated and new ones inserted.
+    pub fn save(&self, tasks: &[Task], next_id: usize) -> Result<(), String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let current = self.write_changes(&tx, tasks, next_id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to save tasks: {}", e))?;
+        *self.saved.borrow_mut() = current;
+        Ok(())
+    }
+
+    /// Replaces the blob-per-task table with the columned one, all in one
+    /// transaction so an interrupted conversion leaves the old table intact.
+    fn convert_legacy(&self, tasks: &[Task], next_id: usize) -> Result<(), String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        tx.execute("DROP TABLE tasks", [])
+            .and_then(|_| tx.execute(CREATE_TASKS, []))
+            .map_err(|e| format!("Failed to convert tasks table: {}", e))?;
+        let current = self.write_changes(&tx, tasks, next_id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to convert tasks table: {}", e))?;
+        *self.saved.borrow_mut() = current;
+        Ok(())
+    }
+
+    /// Issues the row changes inside the caller's transaction and returns the
+    /// snapshots to remember once it commits.
+    fn write_changes(
+        &self,
+        conn: &Connection,
+        tasks: &[Task],
+        next_id: usize,
+    ) -> Result<HashMap<usize, String>, String> {
+        let mut current = HashMap::with_capacity(tasks.len());
+        for task in tasks {
+            if current.insert(task.id, snapshot(task)?).is_some() {
+                return Err(format!(
+                    "Failed to save tasks: duplicate task id {}",
+                    task.id
+                ));
+            }
+        }
+
+        let saved = self.saved.borrow();
+        for id in saved.keys().filter(|id| !current.contains_key(id)) {
+            conn.execute("DELETE FROM tasks WHERE id = ?1", params![*id as i64])
+                .map_err(|e| format!("Failed to delete task {}: {}", id, e))?;
+        }
+        {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "INSERT OR REPLACE INTO tasks ({}) VALUES \
+                     (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                    COLUMNS
+                ))
+                .map_err(|e| format!("Failed to save tasks: {}", e))?;
+            for task in tasks {
+                if saved.get(&task.id) == current.get(&task.id) {
+                    continue;
+                }
+                stmt.execute(params![
+                    task.id as i64,
+                    task.title,
+                    task.description,
+                    task.completed,
+                    task.completed_at.map(|at| at.to_rfc3339()),
+                    task.created_at.to_rfc3339(),
+                    task.due_date
+                        .map(|due| due.format(DUE_DATE_FORMAT).to_string()),
+                    format!("{:?}", task.priority),
+                    task.category.name(),
+                    to_json(&task.tags)?,
+                    to_json(&task.blocked_by)?,
+                    task.parent.map(|p| p as i64),
+                    task.estimate,
+                    task.pinned,
+                    to_json(&task.notes)?,
+                    task.time_spent_minutes,
+                ])
+                .map_err(|e| format!("Failed to save task {}: {}", task.id, e))?;
+            }
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('next_id', ?1)",
+            params![next_id as i64],
+        )
+        .map_err(|e| format!("Failed to save id counter: {}", e))?;
+        Ok(current)
+    }
+
+    fn import_json(&self, json_path: &Path) -> Result<usize, String> {
+        let mut file = load_task_file(json_path)?;
+        let next_id = assign_unique_ids(&mut file.tasks, file.next_id);
+        self.save(&file.tasks, next_id)?;
+        Ok(file.tasks.len())
+    }
+}
+
+/// Reads the tasks of a database that stores each task as a JSON blob in a
+/// `data` column. Returns `None` when the database doesn't use that layout.
+fn load_legacy_tasks(conn: &Connection) -> Result<Option<Vec<Task>>, String> {
+    let columns = conn
+        .prepare("SELECT name FROM pragma_table_info('tasks')")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("Failed to inspect tasks table: {}", e))?;
+    if !columns.iter().any(|c| c == "data") {
+        return Ok(None);
+    }
+
+    let tasks = conn
+        .prepare("SELECT data FROM tasks ORDER BY rowid")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("Failed to query tasks: {}", e))?
+        .iter()
+        .map(|data| serde_json::from_str(data).map_err(|e| format!("Failed to parse task: {}", e)))
+        .collect::<Result<Vec<Task>, String>>()?;
+    Ok(Some(tasks))
+}
+
+/// Rows are keyed by id, so tasks sharing one get fresh ids before they are
+/// stored. Returns the id counter after any reassignment.
+fn assign_unique_ids(tasks: &mut [Task], next_id: usize) -> usize {
+    let mut next_id = next_id.max(tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1);
+    let mut seen = HashSet::new();
+    for task in tasks.iter_mut() {
+        if !seen.insert(task.id) {
+            eprintln!(
+                "Warning: task '{}' shared id {} and was given id {}",
+                task.title, task.id, next_id
+            );
+            task.id = next_id;
+            next_id += 1;
+        }
+    }
+    next_id
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get::<_, i64>(0)? as usize,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        completed: row.get(3)?,
+        completed_at: row
+            .get::<_, Option<String>>(4)?
+            .map(|at| parse_timestamp(4, &at))
+            .transpose()?,
+        created_at: parse_timestamp(5, &row.get::<_, String>(5)?)?,
+        due_date: row
+            .get::<_, Option<String>>(6)?
+            .map(|due| {
+                NaiveDateTime::parse_from_str(&due, DUE_DATE_FORMAT)
+                    .map_err(|e| conversion_error(6, e.to_string()))
+            })
+            .transpose()?,
+        priority: Priority::parse(&row.get::<_, String>(7)?).map_err(|e| conversion_error(7, e))?,
+        category: Category::parse(&row.get::<_, String>(8)?).map_err(|e| conversion_error(8, e))?,
+        tags: from_json(9, &row.get::<_, String>(9)?)?,
+        blocked_by: from_json(10, &row.get::<_, String>(10)?)?,
+        parent: row.get::<_, Option<i64>>(11)?.map(|p| p as usize),
+        estimate: row.get(12)?,
+        pinned: row.get(13)?,
+        notes: from_json(14, &row.get::<_, String>(14)?)?,
+        time_spent_minutes: row.get(15)?,
+    })
+}
+
+fn parse_timestamp(column: usize, text: &str) -> rusqlite::Result<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|at| at.with_timezone(&Local))
+        .map_err(|e| conversion_error(column, e.to_string()))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(column: usize, text: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(text).map_err(|e| conversion_error(column, e.to_string()))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize task: {}", e))
+}
+
+fn snapshot(task: &Task) -> Result<String, String> {
+    to_json(task)
+}
+
+fn conversion_error(column: usize, message: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn task(id: usize, title: &str) -> Task {
+        Task::new(
+            id,
+            title.to_string(),
+            None,
+            None,
+            "medium",
+            "personal",
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn save_only_writes_changed_rows() {
+        let dir = env::temp_dir().join(format!("todo-cli-sqlite-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let store = SqliteStore::open(&dir.join("tasks.db"), &dir.join("tasks.json")).unwrap();
+
+        let mut tasks = vec![task(1, "one"), task(2, "two"), task(3, "three")];
+        store.save(&tasks, 4).unwrap();
+        // Changed behind the store's back; a full rewrite would undo this.
+        store
+            .conn
+            .execute(
+                "UPDATE tasks SET title = 'edited elsewhere' WHERE id = 2",
+                [],
+            )
+            .unwrap();
+
+        tasks[0].completed = true;
+        tasks.remove(2);
+        store.save(&tasks, 4).unwrap();
+
+        let loaded = store.load().unwrap();
+        let summary: Vec<(usize, &str, bool)> = loaded
+            .iter()
+            .map(|t| (t.id, t.title.as_str(), t.completed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(1, "one", true), (2, "edited elsewhere", false)]
+        );
+        assert_eq!(store.load_next_id().unwrap(), 4);
+    }
+}
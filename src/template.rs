@@ -0,0 +1,72 @@
+use crate::{Category, Priority, Task};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// A reusable task skeleton saved with `save-template`. `{date}` in the title
+/// is replaced with today's date when a task is created from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: Priority,
+    pub category: Category,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub estimate: Option<u32>,
+}
+
+/// Templates by name, kept sorted for listing.
+pub type Templates = BTreeMap<String, Template>;
+
+impl Template {
+    pub fn from_task(task: &Task, estimate: Option<u32>) -> Self {
+        Template {
+            title: task.title.clone(),
+            description: task.description.clone(),
+            priority: task.priority,
+            category: task.category.clone(),
+            tags: task.tags.clone(),
+            estimate,
+        }
+    }
+
+    /// Creates a pending task from this template, using `title` instead of the
+    /// template's title pattern when given.
+    pub fn instantiate(&self, id: usize, title: Option<String>) -> Result<Task, String> {
+        let title = title.unwrap_or_else(|| {
+            self.title
+                .replace("{date}", &Local::now().format("%Y-%m-%d").to_string())
+        });
+        let mut task = Task::new(
+            id,
+            title,
+            self.description.clone(),
+            None,
+            &format!("{:?}", self.priority),
+            self.category.name(),
+            Some(self.tags.join(",")),
+        )?;
+        task.estimate = self.estimate;
+        Ok(task)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Templates, String> {
+    if !path.exists() {
+        return Ok(Templates::new());
+    }
+    let contents =
+        fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+pub fn save(path: &Path, templates: &Templates) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(templates)
+        .map_err(|e| format!("Failed to serialize templates: {}", e))?;
+    fs::write(path, contents).map_err(|e| format!("Failed to save templates: {}", e))
+}